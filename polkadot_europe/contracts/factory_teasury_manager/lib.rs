@@ -7,6 +7,7 @@ pub mod factory_teasury_manager {
     use treasury_manager::TreasuryManagerRef;
 
     use ink_lang::codegen::Env;
+    use polkadot_europe::impls::timelock::{self, *};
     use polkadot_europe::traits::factory::*;
 
     use ink_lang::ToAccountId;
@@ -22,6 +23,32 @@ pub mod factory_teasury_manager {
         traits::{Storage, String},
     };
 
+    ///Configuration changes held by the timelock, scale encoded in their ScheduledChange
+    #[derive(Debug, scale::Encode, scale::Decode)]
+    enum FactoryChange {
+        SetTreasuryManagerCodeHash { treasury_manager_code_hash: Hash },
+        SetMinDelay { min_delay: u64 },
+    }
+
+    #[ink(event)]
+    pub struct ev_change_scheduled {
+        #[ink(topic)]
+        id: u32,
+        executable_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_cancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_executed {
+        #[ink(topic)]
+        id: u32,
+    }
+
     //Treasury Manager
     // 0x953eaa1a62a4917abbec2361cbc491fa06d18361fe5fc1ca8eb454bc7be7ece0
 
@@ -30,6 +57,8 @@ pub mod factory_teasury_manager {
     pub struct FactoryTeasuryManager {
         #[storage_field]
         access: access_control::Data,
+        #[storage_field]
+        timelock: timelock::Data,
         admin: AccountId,
         version: u32,
         treasury_manager_code_hash: Hash,
@@ -41,6 +70,23 @@ pub mod factory_teasury_manager {
 
     impl AccessControl for FactoryTeasuryManager {}
 
+    impl Timelock for FactoryTeasuryManager {}
+
+    impl timelock::Internal for FactoryTeasuryManager {
+        fn _emit_change_scheduled(&self, id: u32, executable_at: u64) {
+            self.env()
+                .emit_event(ev_change_scheduled { id, executable_at });
+        }
+
+        fn _emit_change_cancelled(&self, id: u32) {
+            self.env().emit_event(ev_change_cancelled { id });
+        }
+
+        fn _emit_change_executed(&self, id: u32) {
+            self.env().emit_event(ev_change_executed { id });
+        }
+    }
+
     impl Factory for FactoryTeasuryManager {
         //
         #[ink(message, payable)]
@@ -111,6 +157,10 @@ pub mod factory_teasury_manager {
                 instance
                     .grant_role(ADMIN, caller)
                     .expect("Should grant the ADMIN role");
+                instance
+                    .grant_role(GUARDIAN, caller)
+                    .expect("Should grant the GUARDIAN role");
+                instance.timelock.min_delay = DEFAULT_TIMELOCK_DELAY;
                 instance.admin = caller;
                 instance.version = 0;
                 instance.treasury_manager_code_hash = treasury_manager_code_hash;
@@ -134,14 +184,45 @@ pub mod factory_teasury_manager {
             self.admin
         }
 
+        ///Schedule a new treasury manager code hash. execute_change applies it once the timelock delay has passed
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
         pub fn set_treasury_manager_code_hash(
             &mut self,
             new_treasury_manager_code_hash: Hash,
         ) -> Result<(), AccessControlError> {
-            self.treasury_manager_code_hash = new_treasury_manager_code_hash;
-            self.version = 0;
+            let change = FactoryChange::SetTreasuryManagerCodeHash {
+                treasury_manager_code_hash: new_treasury_manager_code_hash,
+            };
+            self._schedule_change(scale::Encode::encode(&change));
+            Ok(())
+        }
+
+        ///Schedule a new timelock delay, applied by execute_change once the current delay has passed
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn set_min_delay(&mut self, min_delay: u64) -> Result<(), AccessControlError> {
+            let change = FactoryChange::SetMinDelay { min_delay };
+            self._schedule_change(scale::Encode::encode(&change));
+            Ok(())
+        }
+
+        ///Apply a scheduled change once its delay has passed. Anyone can call it
+        #[ink(message)]
+        pub fn execute_change(&mut self, id: u32) -> Result<(), TimelockError> {
+            let call = self._take_ready_change(id)?;
+            let change = <FactoryChange as scale::Decode>::decode(&mut &call[..])
+                .map_err(|_| TimelockError::InvalidChange)?;
+            match change {
+                FactoryChange::SetTreasuryManagerCodeHash {
+                    treasury_manager_code_hash,
+                } => {
+                    self.treasury_manager_code_hash = treasury_manager_code_hash;
+                    self.version = 0;
+                }
+                FactoryChange::SetMinDelay { min_delay } => self._set_min_delay(min_delay),
+            }
+            self._emit_change_executed(id);
             Ok(())
         }
     }
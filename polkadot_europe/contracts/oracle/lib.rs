@@ -5,13 +5,14 @@
 mod oracle {
 
     use ink_lang::codegen::Env;
+    use polkadot_europe::impls::timelock::{self, *};
     use polkadot_europe::traits::oracle_dex::*;
 
     use ink_prelude::{vec, vec::Vec};
     use ink_storage::traits::{PackedLayout, SpreadLayout};
     use ink_storage::traits::{SpreadAllocate, StorageLayout};
     use openbrush::{
-        contracts::{access_control::*, traits::errors::PSP22Error, traits::psp22::PSP22Ref},
+        contracts::{access_control::*, pausable::*, traits::psp22::PSP22Ref},
         modifiers,
         storage::Mapping,
         traits::{Storage, String},
@@ -22,188 +23,294 @@ mod oracle {
     pub struct Oracle {
         #[storage_field]
         access: access_control::Data,
-        pair_price: Mapping<(AccountId, AccountId), Balance>,
+        #[storage_field]
+        timelock: timelock::Data,
+        #[storage_field]
+        pause: pausable::Data,
+        pair_price: Mapping<(AccountId, AccountId), PriceData>, //at PRICE_SCALE
         pair_state: Mapping<(AccountId, AccountId), bool>,
-        pool_liquidity: Mapping<AccountId, Balance>,
+        pairs: Vec<(AccountId, AccountId)>, //every pair ever activated, routes are searched through
         pool_state: Mapping<AccountId, bool>,
+        pool_shares: Mapping<((AccountId, AccountId), AccountId), Balance>, //(pair, provider)
+        pool_total_shares: Mapping<(AccountId, AccountId), Balance>,
+
+        price_observations: Mapping<(AccountId, AccountId), Vec<PriceObservation>>,
+        twap_window: u64, //ms the average price is taken over
+
+        reporters: Vec<AccountId>,
+        round_window: u64, //ms a round accepts submissions for
+        quorum: u8,
+        max_deviation_bps: u16, //submissions further than this from the median are outliers
+        price_rounds: Mapping<(AccountId, AccountId), PriceRound>,
+        reporter_stats: Mapping<AccountId, ReporterStats>,
+
+        pricing_modes: Mapping<(AccountId, AccountId), PricingMode>,
+        pair_reserves: Mapping<(AccountId, AccountId), PairReserves>,
+        lp_fee_bps: u16, //taken from the amount in of every swap and kept by the pair's reserves
+        max_swap_deviation_bps: u16, //constant product fills further than this from the oracle price are rejected
+    }
+
+    #[ink(event)]
+    pub struct ev_price_published {
+        #[ink(topic)]
+        base_token: AccountId,
+        #[ink(topic)]
+        quote_token: AccountId,
+        price: Price,
+        submissions: u8,
+    }
+
+    #[ink(event)]
+    pub struct ev_outlier_submission {
+        #[ink(topic)]
+        base_token: AccountId,
+        #[ink(topic)]
+        quote_token: AccountId,
+        #[ink(topic)]
+        reporter: AccountId,
+        price: Price,
+        median: Price,
+    }
+
+    #[ink(event)]
+    pub struct ev_liquidity_added {
+        #[ink(topic)]
+        base_token: AccountId,
+        #[ink(topic)]
+        quote_token: AccountId,
+        #[ink(topic)]
+        provider: AccountId,
+        base_amount: Balance,
+        quote_amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ev_liquidity_removed {
+        #[ink(topic)]
+        base_token: AccountId,
+        #[ink(topic)]
+        quote_token: AccountId,
+        #[ink(topic)]
+        provider: AccountId,
+        base_amount: Balance,
+        quote_amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ev_round_expired {
+        #[ink(topic)]
+        base_token: AccountId,
+        #[ink(topic)]
+        quote_token: AccountId,
+        submissions: u8,
+    }
 
-        average_length: u8,
-        average_prices_pointer: Mapping<(AccountId, AccountId), u8>,
-        average_prices_constituents: Mapping<(AccountId, AccountId), Vec<Balance>>,
-        average_prices: Mapping<(AccountId, AccountId), Balance>,
+    #[ink(event)]
+    pub struct ev_paused {
+        #[ink(topic)]
+        by: AccountId,
+        reason: String,
+    }
+
+    #[ink(event)]
+    pub struct ev_unpaused {
+        #[ink(topic)]
+        by: AccountId,
+        reason: String,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_scheduled {
+        #[ink(topic)]
+        id: u32,
+        executable_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_cancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_executed {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    ///Configuration changes held by the timelock, scale encoded in their ScheduledChange
+    #[derive(Debug, scale::Encode, scale::Decode)]
+    enum OracleChange {
+        ActivatePair {
+            base_token: AccountId,
+            quote_token: AccountId,
+        },
+        SetPricingMode {
+            base_token: AccountId,
+            quote_token: AccountId,
+            mode: PricingMode,
+        },
+        SetSwapParams {
+            lp_fee_bps: u16,
+            max_swap_deviation_bps: u16,
+        },
+        SetTwapWindow {
+            twap_window: u64,
+        },
+        SetMinDelay {
+            min_delay: u64,
+        },
+        AddReporter {
+            reporter: AccountId,
+        },
+        RemoveReporter {
+            reporter: AccountId,
+        },
+        SetRoundParams {
+            round_window: u64,
+            quorum: u8,
+            max_deviation_bps: u16,
+        },
     }
 
     const ADMIN: RoleType = ink_lang::selector_id!("ADMIN");
+    const ORACLE_REPORTER: RoleType = ink_lang::selector_id!("ORACLE_REPORTER");
 
     impl OracleDex for Oracle {
+        ///Submission of a reporter to the pair's current round. The round publishes the median once quorum is reached
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
+        #[modifiers(when_not_paused, only_role(ORACLE_REPORTER))]
         fn update_price(
             &mut self,
             base_token: AccountId,
             quote_token: AccountId,
-            price: Balance,
-        ) -> Result<(), AccessControlError> {
-            if self.get_pair_state(base_token, quote_token) {
-                self.pair_price.insert(&(base_token, quote_token), &price);
-                ink_env::debug_println!(
-                    "update_price: base: {:?} quote: {:?} price: {:?} submitted: {:?}",
-                    &base_token,
-                    &quote_token,
-                    &price,
-                    self.get_pair_state(base_token, quote_token)
-                );
-            }
-            Ok(())
+            price: Price,
+        ) -> Result<(), OracleDexError> {
+            self.submit_to_round(base_token, quote_token, price)
         }
 
         #[ink(message)]
-        fn get_average_length(&self) -> u8 {
-            self.average_length
+        fn get_twap_window(&self) -> u64 {
+            self.twap_window
         }
 
-        // #[ink(message)]
-        fn get_average_prices_pointer(&self, base_token: AccountId, quote_token: AccountId) -> u8 {
-            match self.average_prices_pointer.get(&(base_token, quote_token)) {
-                Some(val) => val,
-                None => 0,
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        fn set_twap_window(&mut self, window: u64) -> Result<(), OracleDexError> {
+            if window == 0 {
+                return Err(OracleDexError::InvalidTwapWindow);
             }
+            self.schedule_change(OracleChange::SetTwapWindow {
+                twap_window: window,
+            });
+            Ok(())
         }
 
         #[ink(message)]
-        fn get_average_price(&self, base_token: AccountId, quote_token: AccountId) -> Balance {
-            match self.average_prices.get(&(base_token, quote_token)) {
-                Some(val) => val,
-                None => 0,
+        fn get_average_price(&self, base_token: AccountId, quote_token: AccountId) -> Price {
+            self.get_twap(base_token, quote_token, self.twap_window)
+        }
+
+        #[ink(message)]
+        fn get_twap(&self, base_token: AccountId, quote_token: AccountId, window: u64) -> Price {
+            let observations = self.get_price_observations(base_token, quote_token);
+            let (first, last) = match (observations.first(), observations.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => return Price::new(0, PRICE_SCALE),
+            };
+            let now = self.env().block_timestamp();
+            //windows reaching before the oldest observation kept are shortened to the history available
+            let start = now.saturating_sub(window).max(first.timestamp);
+            if now <= start {
+                return Price::new(last.price, PRICE_SCALE);
             }
+
+            let cumulative_now = Self::cumulative_price_at(&observations, now);
+            let cumulative_start = Self::cumulative_price_at(&observations, start);
+            Price::new(
+                cumulative_now.wrapping_sub(cumulative_start) / (now - start) as u128,
+                PRICE_SCALE,
+            )
         }
 
         #[ink(message)]
-        fn get_average_prices_constituents(
+        fn get_price_observations(
             &self,
             base_token: AccountId,
             quote_token: AccountId,
-        ) -> Vec<Balance> {
-            match self
-                .average_prices_constituents
+        ) -> Vec<PriceObservation> {
+            self.price_observations
                 .get(&(base_token, quote_token))
-            {
-                Some(valvector) => valvector.clone(),
-                None => Default::default(),
-            }
+                .unwrap_or_default()
         }
 
-        //no need for  #[ink(message)] just for testing
-        // #[ink(message)]
-        fn set_average_prices_pointer(
+        ///Same as update_price. A published median updates both the last price and the pair's cumulative price
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_role(ORACLE_REPORTER))]
+        fn update_average_price(
             &mut self,
             base_token: AccountId,
             quote_token: AccountId,
-            new_pointer: u8,
-        ) {
-            self.average_prices_pointer
-                .insert(&(base_token, quote_token), &new_pointer);
+            price: Price,
+        ) -> Result<(), OracleDexError> {
+            self.submit_to_round(base_token, quote_token, price)
         }
 
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
-        fn update_average_price(
-            &mut self,
+        fn get_pair_price(&self, base_token: AccountId, quote_token: AccountId) -> Price {
+            match self.pair_price.get(&(base_token, quote_token)) {
+                Some(val) => val.price,
+                None => Price::new(0, PRICE_SCALE),
+            }
+        }
+
+        #[ink(message)]
+        fn get_pair_price_data(
+            &self,
             base_token: AccountId,
             quote_token: AccountId,
-            price: Balance,
-        ) -> Result<(), AccessControlError> {
-            if self.get_pair_state(base_token, quote_token) {
-                //
-                let vector_prices_length: u8 = match self
-                    .average_prices_constituents
-                    .get(&(base_token, quote_token))
-                {
-                    Some(valvector) => valvector.len() as u8,
-                    None => 0,
-                };
-
-                let mut pointer = self.get_average_prices_pointer(base_token, quote_token);
-                let mut vector_prices =
-                    self.get_average_prices_constituents(base_token, quote_token);
-
-                ink_env::debug_println!(
-                    "vector_prices_length: {:?} pointer: {:?} vector_prices: {:?}",
-                    &vector_prices_length,
-                    &pointer,
-                    &vector_prices,
-                );
-
-                if vector_prices_length < (self.average_length + 1) {
-                    if vector_prices_length == 0 {
-                        vector_prices.push(price);
-                        vector_prices.push(price); //storing sum at last element
-                    } else {
-                        let sum = vector_prices[(vector_prices_length - 1) as usize];
-                        vector_prices[(vector_prices_length - 1) as usize] = price;
-                        vector_prices.push(sum + price);
-                    }
-
-                    ink_env::debug_println!(
-                        "A> vector_prices_length: {:?} pointer: {:?} vector_prices: {:?}",
-                        &vector_prices_length,
-                        &pointer,
-                        &vector_prices,
-                    );
-                } else {
-                    let outgoing_element = vector_prices[pointer as usize];
-                    //new sum of elements
-                    let sum =
-                        vector_prices[self.average_length as usize] + price - outgoing_element;
-                    vector_prices[self.average_length as usize] = sum;
-                    vector_prices[pointer as usize] = price;
-
-                    if pointer < (self.average_length - 1) {
-                        pointer += 1;
-                    } else {
-                        pointer = 0;
-                    }
-                    self.set_average_prices_pointer(base_token, quote_token, pointer);
-                    let avg_price = sum / (self.average_length as u128);
-                    self.average_prices
-                        .insert(&(base_token, quote_token), &avg_price);
-
-                    ink_env::debug_println!(
-                        "B> vector_prices_length: {:?} pointer: {:?} vector_prices: {:?} avg_price: {:?}",
-                        &vector_prices_length,
-                        &pointer,
-                        &vector_prices,
-                        &avg_price
-                    );
-                    ink_env::debug_println!(
-                        "C> update_average_price: pointer: {:?} average_prices_constituents: {:?} get_average_price: {:?}",
-                        &self.get_average_prices_pointer(base_token, quote_token),
-                        &self.get_average_prices_constituents(base_token, quote_token),
-                        &self.get_average_price(base_token, quote_token),
-                    );
-                }
-
-                self.average_prices_constituents
-                    .insert(&(base_token, quote_token), &vector_prices);
+        ) -> Option<PriceData> {
+            self.pair_price.get(&(base_token, quote_token))
+        }
 
-                ink_env::debug_println!(
-                        "FINAL update_average_price: pointer: {:?} average_prices_constituents: {:?} get_average_price: {:?}",
-                        &self.get_average_prices_pointer(base_token, quote_token),
-                        &self.get_average_prices_constituents(base_token, quote_token),
-                        &self.get_average_price(base_token, quote_token),
-                    );
+        #[ink(message)]
+        fn get_pair_price_checked(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            max_age: u64,
+        ) -> Result<Price, OracleDexError> {
+            let price_data = self.fresh_price_data(base_token, quote_token, max_age)?;
+            Ok(price_data.price)
+        }
 
-                self.update_price(base_token, quote_token, price);
-            }
-            Ok(())
+        #[ink(message)]
+        fn get_average_price_checked(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            max_age: u64,
+        ) -> Result<Price, OracleDexError> {
+            self.get_twap_checked(base_token, quote_token, self.twap_window, max_age)
         }
 
         #[ink(message)]
-        fn get_pair_price(&self, base_token: AccountId, quote_token: AccountId) -> Balance {
-            match self.pair_price.get(&(base_token, quote_token)) {
-                Some(val) => val,
-                None => 0,
+        fn get_twap_checked(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            window: u64,
+            max_age: u64,
+        ) -> Result<Price, OracleDexError> {
+            //the cumulative price is only moved by published prices so the last one dates the twap
+            self.fresh_price_data(base_token, quote_token, max_age)?;
+            let price = self.get_twap(base_token, quote_token, window);
+            if price.is_zero() {
+                return Err(OracleDexError::NoPrice);
             }
+            Ok(price)
         }
 
         #[ink(message)]
@@ -216,10 +323,17 @@ mod oracle {
 
         #[ink(message)]
         fn get_pool_liquidity(&self, token_address: AccountId) -> Balance {
-            match self.pool_liquidity.get(&token_address) {
-                Some(val) => val,
-                None => 0,
-            }
+            self.pairs
+                .iter()
+                .map(|pair| {
+                    let reserves = self.get_pair_reserves(pair.0, pair.1);
+                    match token_address {
+                        token if token == pair.0 => reserves.base_reserve,
+                        token if token == pair.1 => reserves.quote_reserve,
+                        _ => 0,
+                    }
+                })
+                .sum()
         }
 
         #[ink(message)]
@@ -238,13 +352,11 @@ mod oracle {
             &mut self,
             base_token: AccountId,
             quote_token: AccountId,
-        ) -> Result<(), AccessControlError> {
-            if !self.get_pair_state(base_token, quote_token)
-                && self.get_pool_state(base_token)
-                && self.get_pool_state(quote_token)
-            {
-                self.pair_state.insert(&(base_token, quote_token), &true);
-            }
+        ) -> Result<(), OracleDexError> {
+            self.schedule_change(OracleChange::ActivatePair {
+                base_token,
+                quote_token,
+            });
             Ok(())
         }
 
@@ -254,7 +366,7 @@ mod oracle {
             &mut self,
             base_token: AccountId,
             quote_token: AccountId,
-        ) -> Result<(), AccessControlError> {
+        ) -> Result<(), OracleDexError> {
             if self.get_pair_state(base_token, quote_token) {
                 self.pair_state.insert(&(base_token, quote_token), &false);
             }
@@ -263,7 +375,7 @@ mod oracle {
 
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        fn register_pool(&mut self, token: AccountId) -> Result<(), AccessControlError> {
+        fn register_pool(&mut self, token: AccountId) -> Result<(), OracleDexError> {
             if !self.get_pool_state(token) {
                 self.pool_state.insert(&(token), &true);
             }
@@ -271,194 +383,1884 @@ mod oracle {
         }
 
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
         fn add_pool_liquidity(
             &mut self,
-            token: AccountId,
-            amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            if self.get_pool_state(token) {
-                //HERE TRANFER FROM REQUIRES THAT ADMIN HAS APPROVED PSP22 TOKEN
-                match self.make_deposit(token, amount) {
-                    Ok(()) => {
-                        self.pool_liquidity
-                            .insert((&token), &(self.get_pool_liquidity(token) + amount));
-                    }
-                    _ => (),
-                }
+            base_token: AccountId,
+            quote_token: AccountId,
+            base_amount: Balance,
+            quote_amount: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            let pair = (base_token, quote_token);
+            if !self.get_pool_state(base_token) || !self.get_pool_state(quote_token) {
+                return Err(OracleDexError::PoolNotRegistered);
             }
-            Ok(())
+            //paused pairs take no new liquidity, their providers can still withdraw
+            if !self.get_pair_state(base_token, quote_token) {
+                return Err(OracleDexError::PairNotActive);
+            }
+            let provider = self.env().caller();
+            let reserves = self.get_pair_reserves(base_token, quote_token);
+            let total_shares = self.get_pool_total_shares(base_token, quote_token);
+            let (shares, base_in, quote_in) =
+                Self::pool_deposit(&reserves, total_shares, base_amount, quote_amount)?;
+
+            //both allowances are checked before either token moves
+            let contract = self.env().account_id();
+            if PSP22Ref::allowance(&base_token, provider, contract) < base_in
+                || PSP22Ref::allowance(&quote_token, provider, contract) < quote_in
+            {
+                return Err(OracleDexError::InsufficientAllowance);
+            }
+            self.make_deposit(base_token, base_in)?;
+            self.make_deposit(quote_token, quote_in)?;
+
+            self.pair_reserves.insert(
+                &pair,
+                &PairReserves {
+                    base_reserve: reserves.base_reserve + base_in,
+                    quote_reserve: reserves.quote_reserve + quote_in,
+                },
+            );
+            self.pool_shares.insert(
+                &(pair, provider),
+                &(self.get_pool_shares(base_token, quote_token, provider) + shares),
+            );
+            self.pool_total_shares
+                .insert(&pair, &(total_shares + shares));
+
+            self.env().emit_event(ev_liquidity_added {
+                base_token,
+                quote_token,
+                provider,
+                base_amount: base_in,
+                quote_amount: quote_in,
+                shares,
+            });
+            Ok(shares)
         }
 
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
         fn withdraw_pool_liquidity(
             &mut self,
-            token: AccountId,
-            amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            if self.pool_state.get(&token).unwrap() {
-                let current_liquidity = self.get_pool_liquidity(token);
-                if amount <= current_liquidity {
-                    //HERE TRANFER TO ADMIN
-                    self.admin_withdrawal(token, amount);
-                    self.pool_liquidity
-                        .insert((&token), &(current_liquidity - amount));
-                }
+            base_token: AccountId,
+            quote_token: AccountId,
+            shares: Balance,
+        ) -> Result<(Balance, Balance), OracleDexError> {
+            let pair = (base_token, quote_token);
+            let provider = self.env().caller();
+            let provider_shares = self.get_pool_shares(base_token, quote_token, provider);
+            if shares == 0 || shares > provider_shares {
+                return Err(OracleDexError::InsufficientShares);
             }
-            Ok(())
+            let reserves = self.get_pair_reserves(base_token, quote_token);
+            let total_shares = self.get_pool_total_shares(base_token, quote_token);
+            let (base_out, quote_out) = Self::pool_redemption(&reserves, total_shares, shares);
+
+            self.pool_shares
+                .insert(&(pair, provider), &(provider_shares - shares));
+            self.pool_total_shares
+                .insert(&pair, &(total_shares - shares));
+            self.pair_reserves.insert(
+                &pair,
+                &PairReserves {
+                    base_reserve: reserves.base_reserve - base_out,
+                    quote_reserve: reserves.quote_reserve - quote_out,
+                },
+            );
+
+            //HERE TRANFER TO PROVIDER
+            self.withdraw_to_caller(base_token, base_out)?;
+            self.withdraw_to_caller(quote_token, quote_out)?;
+
+            self.env().emit_event(ev_liquidity_removed {
+                base_token,
+                quote_token,
+                provider,
+                base_amount: base_out,
+                quote_amount: quote_out,
+                shares,
+            });
+            Ok((base_out, quote_out))
+        }
+
+        #[ink(message)]
+        fn get_pool_shares(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            provider: AccountId,
+        ) -> Balance {
+            self.pool_shares
+                .get(&((base_token, quote_token), provider))
+                .unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn get_pool_total_shares(&self, base_token: AccountId, quote_token: AccountId) -> Balance {
+            self.pool_total_shares
+                .get(&(base_token, quote_token))
+                .unwrap_or(0)
         }
 
         // PSP22Error
         #[ink(message)]
+        #[modifiers(when_not_paused)]
         fn swap(
             &mut self,
             deposited_token: AccountId,
             withdrawn_token: AccountId,
             amount: Balance,
-            swap_caller: AccountId,
+            min_amount_out: Balance,
+            deadline: u64,
             use_average_price: bool,
-        ) -> Result<Balance, AccessControlError> {
-            //
-            let mut price: Balance = Default::default();
-            let mut withdrawn_amount: Balance = Default::default();
-
-            if self.get_pair_state(deposited_token, withdrawn_token) {
-                match use_average_price {
-                    true => price = self.get_average_price(deposited_token, withdrawn_token),
-                    false => price = self.get_pair_price(deposited_token, withdrawn_token),
-                }
-                // price = self.get_pair_price(deposited_token, withdrawn_token);
-                withdrawn_amount = amount * price;
-            } else if self.get_pair_state(withdrawn_token, deposited_token) {
-                match use_average_price {
-                    true => price = self.get_average_price(withdrawn_token, deposited_token),
-                    false => price = self.get_pair_price(withdrawn_token, deposited_token),
-                }
-                // price = self.get_pair_price(withdrawn_token, deposited_token);
-                withdrawn_amount = amount / price;
-            } else {
-                assert!(1 > 2, "This Pair does not exist");
+        ) -> Result<Balance, OracleDexError> {
+            if self.env().block_timestamp() > deadline {
+                return Err(OracleDexError::DeadlineExpired);
+            }
+            let withdrawn_amount =
+                self.hop_amount_out(deposited_token, withdrawn_token, amount, use_average_price)?;
+            if withdrawn_amount == 0 {
+                return Err(OracleDexError::InsufficientOutputAmount);
+            }
+            if withdrawn_amount < min_amount_out {
+                return Err(OracleDexError::SlippageExceeded);
             }
 
-            if withdrawn_amount > 0 && withdrawn_amount <= self.get_pool_liquidity(withdrawn_token)
-            {
-                ink_env::debug_println!(
-                    "swap: amount: {:?} withdrawn_amount: {:?} liquidity: {:?} caller: {:?}",
-                    amount,
-                    withdrawn_amount,
-                    self.get_pool_liquidity(withdrawn_token),
-                    self.env().caller().clone()
-                );
+            ink_env::debug_println!(
+                "swap: amount: {:?} withdrawn_amount: {:?} caller: {:?}",
+                amount,
+                withdrawn_amount,
+                self.env().caller().clone()
+            );
 
-                self.make_deposit(deposited_token, amount);
+            self.make_deposit(deposited_token, amount)?;
+            self.settle_hop(deposited_token, withdrawn_token, amount, withdrawn_amount)?;
 
-                self.pool_liquidity.insert(
-                    (&deposited_token),
-                    &(self.get_pool_liquidity(deposited_token) + amount),
-                );
+            self.approve_token_for_swap(
+                withdrawn_token,
+                self.env().caller().clone(),
+                withdrawn_amount,
+            )?;
 
-                self.pool_liquidity.insert(
-                    (&withdrawn_token),
-                    &(self.get_pool_liquidity(withdrawn_token) - withdrawn_amount),
-                );
+            ink_env::debug_println!(
+                "swap: deposited_token: {:?} quote: {:?} submitted: {:?}",
+                &deposited_token,
+                &withdrawn_token,
+                &withdrawn_amount,
+            );
+            Ok(withdrawn_amount)
+        }
 
-                self.approve_token_for_swap(
-                    withdrawn_token,
-                    self.env().caller().clone(),
-                    withdrawn_amount,
-                );
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        fn swap_exact_in(
+            &mut self,
+            path: Vec<AccountId>,
+            amount: Balance,
+            min_amount_out: Balance,
+            deadline: u64,
+        ) -> Result<Balance, OracleDexError> {
+            if self.env().block_timestamp() > deadline {
+                return Err(OracleDexError::DeadlineExpired);
+            }
+            //every hop is quoted before anything moves, a path never repeats a token so hops do not affect each other
+            let amounts = self.path_amounts_out(&path, amount)?;
+            let amount_out = amounts[amounts.len() - 1];
+            if amount_out == 0 {
+                return Err(OracleDexError::InsufficientOutputAmount);
+            }
+            if amount_out < min_amount_out {
+                return Err(OracleDexError::SlippageExceeded);
+            }
 
-                ink_env::debug_println!(
-                    "swap: deposited_token: {:?} quote: {:?} price: {:?} submitted: {:?} swap_caller: {:?}",
-                    &deposited_token,
-                    &withdrawn_token,
-                    &price,
-                    &withdrawn_amount,
-                    &swap_caller,
-                );
+            self.make_deposit(path[0], amount)?;
+            for hop in 0..path.len() - 1 {
+                self.settle_hop(path[hop], path[hop + 1], amounts[hop], amounts[hop + 1])?;
             }
-            Ok(withdrawn_amount)
+
+            self.approve_token_for_swap(
+                path[path.len() - 1],
+                self.env().caller().clone(),
+                amount_out,
+            )?;
+
+            ink_env::debug_println!(
+                "swap_exact_in: path: {:?} amounts: {:?} caller: {:?}",
+                &path,
+                &amounts,
+                self.env().caller().clone()
+            );
+            Ok(amount_out)
         }
-    }
 
-    impl Oracle {
-        #[ink(constructor)]
-        pub fn new() -> Self {
-            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
-                let caller = instance.env().caller();
-                instance._init_with_admin(caller);
-                instance
-                    .grant_role(ADMIN, caller)
-                    .expect("Should grant the role");
-                instance.pair_price = Default::default();
-                instance.pair_state = Default::default();
-                instance.pool_liquidity = Default::default();
-                instance.pool_state = Default::default();
+        #[ink(message)]
+        fn get_amount_out_path(
+            &self,
+            path: Vec<AccountId>,
+            amount_in: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            let amounts = self.path_amounts_out(&path, amount_in)?;
+            Ok(amounts[amounts.len() - 1])
+        }
 
-                instance.average_length = 5;
-                instance.average_prices_pointer = Default::default();
-                instance.average_prices_constituents = Default::default();
-                instance.average_prices = Default::default();
-            })
+        #[ink(message)]
+        fn get_best_route(
+            &self,
+            token_in: AccountId,
+            token_out: AccountId,
+            amount_in: Balance,
+        ) -> Option<(Vec<AccountId>, Balance)> {
+            let mut best = None;
+            self.extend_route(&mut vec![token_in], token_out, amount_in, &mut best);
+            best
         }
 
-        fn approve_token_for_swap(
+        #[ink(message)]
+        fn get_route_price_checked(
+            &self,
+            path: Vec<AccountId>,
+            use_average_price: bool,
+            max_age: u64,
+        ) -> Result<Price, OracleDexError> {
+            if path.len() < 2 || path.len() > MAX_PATH_LENGTH as usize {
+                return Err(OracleDexError::InvalidPath);
+            }
+            let mut route_price = Price::new(PRICE_SCALE, PRICE_SCALE);
+            for hop in path.windows(2) {
+                let hop_price =
+                    self.hop_price_checked(hop[0], hop[1], use_average_price, max_age)?;
+                route_price = route_price
+                    .chain(&hop_price)
+                    .ok_or(OracleDexError::InvalidPrice)?;
+            }
+            if route_price.is_zero() {
+                return Err(OracleDexError::NoPrice);
+            }
+            Ok(route_price)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        fn set_pricing_mode(
             &mut self,
-            withdrawn_token: AccountId,
-            contract_address: AccountId,
-            amount: Balance,
-        ) -> Result<(), PSP22Error> {
-            PSP22Ref::approve(&withdrawn_token, contract_address, amount)
-                .expect("Approval for deposited_token did not go as planned");
+            base_token: AccountId,
+            quote_token: AccountId,
+            mode: PricingMode,
+        ) -> Result<(), OracleDexError> {
+            if self.pair_state.get(&(base_token, quote_token)).is_none() {
+                return Err(OracleDexError::PairNotActive);
+            }
+            self.schedule_change(OracleChange::SetPricingMode {
+                base_token,
+                quote_token,
+                mode,
+            });
             Ok(())
         }
 
-        fn admin_withdrawal(
-            &mut self,
-            token_address: AccountId,
-            amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            PSP22Ref::transfer(
-                &token_address,
-                self.env().caller(),
-                amount,
-                Vec::<u8>::new(),
-            )
-            .expect("Transfer to ADMIN did not go well");
+        #[ink(message)]
+        fn get_pricing_mode(&self, base_token: AccountId, quote_token: AccountId) -> PricingMode {
+            self.pricing_modes
+                .get(&(base_token, quote_token))
+                .unwrap_or_default()
+        }
 
-            //SHOULD EMMIT EVENT
+        #[ink(message)]
+        fn get_pair_reserves(&self, base_token: AccountId, quote_token: AccountId) -> PairReserves {
+            self.pair_reserves
+                .get(&(base_token, quote_token))
+                .unwrap_or_default()
+        }
 
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        fn set_swap_params(
+            &mut self,
+            lp_fee_bps: u16,
+            max_swap_deviation_bps: u16,
+        ) -> Result<(), OracleDexError> {
+            if lp_fee_bps as u128 >= BPS {
+                return Err(OracleDexError::InvalidSwapParams);
+            }
+            self.schedule_change(OracleChange::SetSwapParams {
+                lp_fee_bps,
+                max_swap_deviation_bps,
+            });
             Ok(())
         }
 
-        fn make_deposit(
-            &mut self,
-            token_address: AccountId,
-            amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            let from_caller = self.env().caller().clone();
-            let contract = self.env().account_id().clone();
+        #[ink(message)]
+        fn get_swap_params(&self) -> (u16, u16) {
+            (self.lp_fee_bps, self.max_swap_deviation_bps)
+        }
 
-            assert!(
-                PSP22Ref::allowance(&token_address, from_caller, contract) >= amount,
-                "allowance is too low"
-            );
+        #[ink(message)]
+        fn get_amount_out(
+            &self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+            amount_in: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            self.hop_amount_out(deposited_token, withdrawn_token, amount_in, false)
+        }
 
-            PSP22Ref::transfer_from_builder(
-                &token_address,
-                from_caller,
-                contract,
-                amount,
-                Vec::<u8>::new(),
-            )
-            .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
-            .fire()
-            .unwrap();
+        #[ink(message)]
+        fn get_amount_in(
+            &self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+            amount_out: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            let (pair, deposited_is_base) = self.swap_pair(deposited_token, withdrawn_token)?;
+            match self.get_pricing_mode(pair.0, pair.1) {
+                PricingMode::OraclePrice => self.before_lp_fee(self.oracle_amount_in(
+                    pair,
+                    deposited_is_base,
+                    amount_out,
+                )?),
+                PricingMode::ConstantProduct => {
+                    let (reserve_in, reserve_out) = self.reserves_in_out(pair, deposited_is_base);
+                    self.constant_product_amount_in(reserve_in, reserve_out, amount_out)
+                }
+            }
+        }
+    }
+
+    impl Timelock for Oracle {}
+
+    impl Pausable for Oracle {}
+
+    impl timelock::Internal for Oracle {
+        fn _emit_change_scheduled(&self, id: u32, executable_at: u64) {
+            self.env()
+                .emit_event(ev_change_scheduled { id, executable_at });
+        }
+
+        fn _emit_change_cancelled(&self, id: u32) {
+            self.env().emit_event(ev_change_cancelled { id });
+        }
+
+        fn _emit_change_executed(&self, id: u32) {
+            self.env().emit_event(ev_change_executed { id });
+        }
+    }
+
+    impl Oracle {
+        ///GUARDIAN halts price updates and swaps. Queries keep working
+        #[ink(message)]
+        #[modifiers(only_role(GUARDIAN))]
+        pub fn pause(&mut self, reason: String) -> Result<(), OracleDexError> {
+            self._pause::<OracleDexError>()?;
+            self.env().emit_event(ev_paused {
+                by: self.env().caller(),
+                reason,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn unpause(&mut self, reason: String) -> Result<(), OracleDexError> {
+            self._unpause::<OracleDexError>()?;
+            self.env().emit_event(ev_unpaused {
+                by: self.env().caller(),
+                reason,
+            });
+            Ok(())
+        }
+
+        ///Schedule a new timelock delay, applied by execute_change once the current delay has passed
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn set_min_delay(&mut self, min_delay: u64) -> Result<(), OracleDexError> {
+            self.schedule_change(OracleChange::SetMinDelay { min_delay });
+            Ok(())
+        }
+
+        ///Apply a scheduled change once its delay has passed. Anyone can call it
+        #[ink(message)]
+        pub fn execute_change(&mut self, id: u32) -> Result<(), OracleDexError> {
+            let call = self
+                .get_scheduled_change(id)
+                .ok_or(TimelockError::UnknownChange)?
+                .call;
+            let change = <OracleChange as scale::Decode>::decode(&mut &call[..])
+                .map_err(|_| TimelockError::InvalidChange)?;
+            //reporters or quorum may have changed since scheduling, the change stays scheduled if it no longer fits
+            self.check_reporter_change(&change)?;
+            self._take_ready_change(id)?;
+            match change {
+                OracleChange::ActivatePair {
+                    base_token,
+                    quote_token,
+                } => self._activate_pair(base_token, quote_token),
+                OracleChange::SetPricingMode {
+                    base_token,
+                    quote_token,
+                    mode,
+                } => {
+                    self.pricing_modes.insert(&(base_token, quote_token), &mode);
+                }
+                OracleChange::SetSwapParams {
+                    lp_fee_bps,
+                    max_swap_deviation_bps,
+                } => {
+                    self.lp_fee_bps = lp_fee_bps;
+                    self.max_swap_deviation_bps = max_swap_deviation_bps;
+                }
+                OracleChange::SetTwapWindow { twap_window } => self.twap_window = twap_window,
+                OracleChange::SetMinDelay { min_delay } => self._set_min_delay(min_delay),
+                OracleChange::AddReporter { reporter } => {
+                    if !self.reporters.contains(&reporter) {
+                        self._setup_role(ORACLE_REPORTER, reporter);
+                        self.reporters.push(reporter);
+                    }
+                }
+                OracleChange::RemoveReporter { reporter } => {
+                    if let Some(position) = self
+                        .reporters
+                        .iter()
+                        .position(|account| *account == reporter)
+                    {
+                        self._do_revoke_role(ORACLE_REPORTER, reporter);
+                        self.reporters.remove(position);
+                    }
+                }
+                OracleChange::SetRoundParams {
+                    round_window,
+                    quorum,
+                    max_deviation_bps,
+                } => {
+                    self.round_window = round_window;
+                    self.quorum = quorum;
+                    self.max_deviation_bps = max_deviation_bps;
+                }
+            }
+            self._emit_change_executed(id);
+            Ok(())
+        }
+
+        fn schedule_change(&mut self, change: OracleChange) -> u32 {
+            self._schedule_change(scale::Encode::encode(&change))
+        }
+
+        ///Reporters must always be able to reach the quorum
+        fn check_reporter_change(&self, change: &OracleChange) -> Result<(), OracleDexError> {
+            let (reporters, quorum) = match change {
+                OracleChange::RemoveReporter { reporter } if self.reporters.contains(reporter) => {
+                    (self.reporters.len() - 1, self.quorum)
+                }
+                OracleChange::SetRoundParams {
+                    round_window,
+                    quorum,
+                    ..
+                } => {
+                    if *quorum == 0 || *round_window == 0 {
+                        return Err(OracleDexError::InvalidRoundParams);
+                    }
+                    (self.reporters.len(), *quorum)
+                }
+                _ => return Ok(()),
+            };
+            if quorum as usize > reporters {
+                return Err(OracleDexError::InvalidRoundParams);
+            }
+            Ok(())
+        }
+
+        fn _activate_pair(&mut self, base_token: AccountId, quote_token: AccountId) {
+            if !self.get_pair_state(base_token, quote_token)
+                && self.get_pool_state(base_token)
+                && self.get_pool_state(quote_token)
+            {
+                self.pair_state.insert(&(base_token, quote_token), &true);
+                if !self.pairs.contains(&(base_token, quote_token)) {
+                    self.pairs.push((base_token, quote_token));
+                }
+            }
+        }
+
+        ///Every pair ever activated as (base_token, quote_token), active or paused
+        #[ink(message)]
+        pub fn get_pairs(&self) -> Vec<(AccountId, AccountId)> {
+            self.pairs.clone()
+        }
+
+        ///Schedule adding reporter. execute_change applies it once the timelock delay has passed
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn add_reporter(&mut self, reporter: AccountId) -> Result<(), OracleDexError> {
+            self.schedule_change(OracleChange::AddReporter { reporter });
+            Ok(())
+        }
+
+        ///Schedule removing reporter. Fails if the reporters left could not reach the quorum
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn remove_reporter(&mut self, reporter: AccountId) -> Result<(), OracleDexError> {
+            let change = OracleChange::RemoveReporter { reporter };
+            self.check_reporter_change(&change)?;
+            self.schedule_change(change);
+            Ok(())
+        }
+
+        ///Schedule round_window in ms, quorum of submissions publishing a price and max_deviation_bps
+        ///from the median. quorum cannot exceed the number of reporters
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn set_round_params(
+            &mut self,
+            round_window: u64,
+            quorum: u8,
+            max_deviation_bps: u16,
+        ) -> Result<(), OracleDexError> {
+            let change = OracleChange::SetRoundParams {
+                round_window,
+                quorum,
+                max_deviation_bps,
+            };
+            self.check_reporter_change(&change)?;
+            self.schedule_change(change);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_reporters(&self) -> Vec<AccountId> {
+            self.reporters.clone()
+        }
+
+        #[ink(message)]
+        pub fn get_round_params(&self) -> (u64, u8, u16) {
+            (self.round_window, self.quorum, self.max_deviation_bps)
+        }
+
+        #[ink(message)]
+        pub fn get_reporter_stats(&self, reporter: AccountId) -> ReporterStats {
+            self.reporter_stats.get(&reporter).unwrap_or_default()
+        }
+
+        ///Latest round of the pair, finalized once it published a price
+        #[ink(message)]
+        pub fn get_price_round(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+        ) -> Option<PriceRound> {
+            self.price_rounds.get(&(base_token, quote_token))
+        }
+
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            ink_lang::codegen::initialize_contract(|instance: &mut Self| {
+                let caller = instance.env().caller();
+                instance._init_with_admin(caller);
+                instance
+                    .grant_role(ADMIN, caller)
+                    .expect("Should grant the role");
+                instance
+                    .grant_role(GUARDIAN, caller)
+                    .expect("Should grant the GUARDIAN role");
+                instance.timelock.min_delay = DEFAULT_TIMELOCK_DELAY;
+                instance.pair_price = Default::default();
+                instance.pair_state = Default::default();
+                instance.pairs = Default::default();
+                instance.pool_state = Default::default();
+                instance.pool_shares = Default::default();
+                instance.pool_total_shares = Default::default();
+
+                instance.price_observations = Default::default();
+                instance.twap_window = 3_600_000;
+
+                instance
+                    .grant_role(ORACLE_REPORTER, caller)
+                    .expect("Should grant the ORACLE_REPORTER role");
+                instance.reporters = vec![caller];
+                instance.round_window = 60_000;
+                instance.quorum = 1;
+                instance.max_deviation_bps = 500;
+                instance.price_rounds = Default::default();
+                instance.reporter_stats = Default::default();
+
+                instance.pricing_modes = Default::default();
+                instance.pair_reserves = Default::default();
+                instance.lp_fee_bps = 30;
+                instance.max_swap_deviation_bps = 300;
+            })
+        }
+
+        ///Store price as the pair's last price and accumulate the previous one in the pair's cumulative price
+        fn publish_price(&mut self, base_token: AccountId, quote_token: AccountId, price: Price) {
+            let now = self.env().block_timestamp();
+            let mut observations = self.get_price_observations(base_token, quote_token);
+            let cumulative_price = Self::cumulative_price_at(&observations, now);
+            let observation = PriceObservation {
+                timestamp: now,
+                cumulative_price,
+                price: price.mantissa,
+            };
+            //a later price in the same block replaces the earlier one
+            match observations.last_mut() {
+                Some(last) if last.timestamp == now => *last = observation,
+                _ => observations.push(observation),
+            }
+            if observations.len() > MAX_PRICE_OBSERVATIONS as usize {
+                observations.remove(0);
+            }
+            self.price_observations
+                .insert(&(base_token, quote_token), &observations);
+
+            let price_data = PriceData {
+                price,
+                timestamp: self.env().block_timestamp(),
+                block_number: self.env().block_number(),
+            };
+            self.pair_price
+                .insert(&(base_token, quote_token), &price_data);
+            ink_env::debug_println!(
+                "publish_price: base: {:?} quote: {:?} price: {:?}",
+                &base_token,
+                &quote_token,
+                &price
+            );
+        }
+
+        ///Cumulative price at timestamp, extrapolating the last observation at or before it. Wraps on overflow
+        ///so only differences of cumulative prices are meaningful
+        fn cumulative_price_at(observations: &Vec<PriceObservation>, timestamp: u64) -> u128 {
+            match observations
+                .iter()
+                .rev()
+                .find(|observation| observation.timestamp <= timestamp)
+            {
+                Some(observation) => observation.cumulative_price.wrapping_add(
+                    observation
+                        .price
+                        .wrapping_mul((timestamp - observation.timestamp) as u128),
+                ),
+                None => 0,
+            }
+        }
+
+        ///Add the reporter's price to the pair's round. A round stays open for round_window whether or not it published.
+        ///Once its window is over it is closed by the next submission, which counts a missed round for every reporter
+        ///that did not submit to it, and a new round is started.
+        ///Reaching quorum publishes the median and flags outliers. Later submissions within the window take part in
+        ///the round and are checked against the published median
+        fn submit_to_round(
+            &mut self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            price: Price,
+        ) -> Result<(), OracleDexError> {
+            if !self.get_pair_state(base_token, quote_token) {
+                return Err(OracleDexError::PairNotActive);
+            }
+            //prices are stored with PRICE_SCALE whatever scale they are submitted with
+            let price = price
+                .rescale(PRICE_SCALE)
+                .filter(|price| !price.is_zero())
+                .ok_or(OracleDexError::InvalidPrice)?;
+
+            let now = self.env().block_timestamp();
+            let reporter = self.env().caller();
+            let pair = (base_token, quote_token);
+
+            let mut round = match self.price_rounds.get(&pair) {
+                Some(round) if now <= round.started_at + self.round_window => round,
+                Some(round) => {
+                    if !round.finalized {
+                        self.env().emit_event(ev_round_expired {
+                            base_token,
+                            quote_token,
+                            submissions: round.submissions.len() as u8,
+                        });
+                    }
+                    //the deadline has passed, whoever has not submitted missed the round
+                    self.count_missed_round(&round);
+                    PriceRound {
+                        id: round.id + 1,
+                        started_at: now,
+                        submissions: Vec::new(),
+                        finalized: false,
+                    }
+                }
+                None => PriceRound {
+                    id: 0,
+                    started_at: now,
+                    submissions: Vec::new(),
+                    finalized: false,
+                },
+            };
+
+            if round
+                .submissions
+                .iter()
+                .any(|(submitter, _)| *submitter == reporter)
+            {
+                return Err(OracleDexError::AlreadySubmitted);
+            }
+            round.submissions.push((reporter, price.mantissa));
+            let mut stats = self.get_reporter_stats(reporter);
+            stats.submissions += 1;
+            self.reporter_stats.insert(&reporter, &stats);
+
+            if round.finalized {
+                if let Some(published) = self.pair_price.get(&pair) {
+                    self.flag_outlier(
+                        base_token,
+                        quote_token,
+                        reporter,
+                        price.mantissa,
+                        published.price.mantissa,
+                    );
+                }
+            } else if round.submissions.len() >= self.quorum as usize {
+                let median = Self::median(&round.submissions);
+                self.flag_outliers(base_token, quote_token, &round, median);
+                round.finalized = true;
+
+                self.publish_price(base_token, quote_token, Price::new(median, PRICE_SCALE));
+                self.env().emit_event(ev_price_published {
+                    base_token,
+                    quote_token,
+                    price: Price::new(median, PRICE_SCALE),
+                    submissions: round.submissions.len() as u8,
+                });
+            }
+            self.price_rounds.insert(&pair, &round);
+
+            Ok(())
+        }
+
+        ///Middle submission, or the mean of the two middle ones for an even count
+        fn median(submissions: &Vec<(AccountId, u128)>) -> u128 {
+            let mut mantissas: Vec<u128> =
+                submissions.iter().map(|(_, mantissa)| *mantissa).collect();
+            mantissas.sort_unstable();
+            let middle = mantissas.len() / 2;
+            if mantissas.len() % 2 == 0 {
+                mantissas[middle - 1] / 2
+                    + mantissas[middle] / 2
+                    + (mantissas[middle - 1] % 2 + mantissas[middle] % 2) / 2
+            } else {
+                mantissas[middle]
+            }
+        }
+
+        fn flag_outliers(
+            &mut self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            round: &PriceRound,
+            median: u128,
+        ) {
+            for (reporter, mantissa) in round.submissions.iter() {
+                self.flag_outlier(base_token, quote_token, *reporter, *mantissa, median);
+            }
+        }
+
+        fn flag_outlier(
+            &mut self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            reporter: AccountId,
+            mantissa: u128,
+            median: u128,
+        ) {
+            let deviation = mantissa.abs_diff(median);
+            let deviation_bps =
+                mul_div(deviation, 10_000, median, Rounding::Down).unwrap_or(u128::MAX);
+            if deviation_bps > self.max_deviation_bps as u128 {
+                let mut stats = self.get_reporter_stats(reporter);
+                stats.outliers += 1;
+                self.reporter_stats.insert(&reporter, &stats);
+
+                self.env().emit_event(ev_outlier_submission {
+                    base_token,
+                    quote_token,
+                    reporter,
+                    price: Price::new(mantissa, PRICE_SCALE),
+                    median: Price::new(median, PRICE_SCALE),
+                });
+            }
+        }
+
+        fn count_missed_round(&mut self, round: &PriceRound) {
+            for reporter in self.reporters.clone() {
+                if !round
+                    .submissions
+                    .iter()
+                    .any(|(submitter, _)| *submitter == reporter)
+                {
+                    let mut stats = self.get_reporter_stats(reporter);
+                    stats.missed_rounds += 1;
+                    self.reporter_stats.insert(&reporter, &stats);
+                }
+            }
+        }
+
+        fn fresh_price_data(
+            &self,
+            base_token: AccountId,
+            quote_token: AccountId,
+            max_age: u64,
+        ) -> Result<PriceData, OracleDexError> {
+            let price_data = self
+                .pair_price
+                .get(&(base_token, quote_token))
+                .ok_or(OracleDexError::NoPrice)?;
+            if price_data.price.is_zero() {
+                return Err(OracleDexError::NoPrice);
+            }
+            if self
+                .env()
+                .block_timestamp()
+                .saturating_sub(price_data.timestamp)
+                > max_age
+            {
+                return Err(OracleDexError::StalePrice);
+            }
+            Ok(price_data)
+        }
+
+        ///Active pair key of a swap and whether deposited_token is its base token
+        fn swap_pair(
+            &self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+        ) -> Result<((AccountId, AccountId), bool), OracleDexError> {
+            if self.get_pair_state(deposited_token, withdrawn_token) {
+                Ok(((deposited_token, withdrawn_token), true))
+            } else if self.get_pair_state(withdrawn_token, deposited_token) {
+                Ok(((withdrawn_token, deposited_token), false))
+            } else {
+                Err(OracleDexError::PairNotActive)
+            }
+        }
+
+        ///withdrawn_token a swap of amount_in returns at the pair's pricing. Fails if the pair's reserves cannot
+        ///cover it or a constant product fill strays from the oracle price
+        fn hop_amount_out(
+            &self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+            amount_in: Balance,
+            use_average_price: bool,
+        ) -> Result<Balance, OracleDexError> {
+            let (pair, deposited_is_base) = self.swap_pair(deposited_token, withdrawn_token)?;
+            let (reserve_in, reserve_out) = self.reserves_in_out(pair, deposited_is_base);
+            //the reserves keep what rounding leaves over
+            match self.get_pricing_mode(pair.0, pair.1) {
+                PricingMode::OraclePrice => {
+                    //the lp fee stays in the deposited_token reserve
+                    let amount_out = self.oracle_amount_out(
+                        pair,
+                        deposited_is_base,
+                        self.after_lp_fee(amount_in)?,
+                        use_average_price,
+                    )?;
+                    if amount_out > reserve_out {
+                        return Err(OracleDexError::InsufficientLiquidity);
+                    }
+                    Ok(amount_out)
+                }
+                PricingMode::ConstantProduct => {
+                    let amount_out =
+                        self.constant_product_amount_out(reserve_in, reserve_out, amount_in)?;
+                    self.check_swap_deviation(pair, deposited_is_base, amount_in, amount_out)?;
+                    Ok(amount_out)
+                }
+            }
+        }
+
+        ///Book a quoted hop in the reserves of its pair, whatever its pricing mode
+        fn settle_hop(
+            &mut self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+            amount_in: Balance,
+            amount_out: Balance,
+        ) -> Result<(), OracleDexError> {
+            let (pair, deposited_is_base) = self.swap_pair(deposited_token, withdrawn_token)?;
+            let (reserve_in, reserve_out) = self.reserves_in_out(pair, deposited_is_base);
+            if amount_out > reserve_out {
+                return Err(OracleDexError::InsufficientLiquidity);
+            }
+            self.set_reserves_in_out(
+                pair,
+                deposited_is_base,
+                reserve_in + amount_in,
+                reserve_out - amount_out,
+            );
+            Ok(())
+        }
+
+        ///Amount of every token along path, starting with amount_in
+        fn path_amounts_out(
+            &self,
+            path: &Vec<AccountId>,
+            amount_in: Balance,
+        ) -> Result<Vec<Balance>, OracleDexError> {
+            if path.len() < 2 || path.len() > MAX_PATH_LENGTH as usize {
+                return Err(OracleDexError::InvalidPath);
+            }
+            for (position, token) in path.iter().enumerate() {
+                if path[position + 1..].contains(token) {
+                    return Err(OracleDexError::InvalidPath);
+                }
+            }
+
+            let mut amounts = vec![amount_in];
+            for hop in path.windows(2) {
+                let amount_out =
+                    self.hop_amount_out(hop[0], hop[1], amounts[amounts.len() - 1], false)?;
+                amounts.push(amount_out);
+            }
+            Ok(amounts)
+        }
+
+        ///Depth first search of the paths from the last token of path to token_out, keeping the best fill in best
+        fn extend_route(
+            &self,
+            path: &mut Vec<AccountId>,
+            token_out: AccountId,
+            amount_in: Balance,
+            best: &mut Option<(Vec<AccountId>, Balance)>,
+        ) {
+            let last_token = path[path.len() - 1];
+            if last_token == token_out {
+                if let Ok(amount_out) = self.get_amount_out_path(path.clone(), amount_in) {
+                    let improves = match best {
+                        Some((_, best_amount)) => amount_out > *best_amount,
+                        None => amount_out > 0,
+                    };
+                    if improves {
+                        *best = Some((path.clone(), amount_out));
+                    }
+                }
+                return;
+            }
+            if path.len() >= MAX_PATH_LENGTH as usize {
+                return;
+            }
+
+            for (base_token, quote_token) in self.pairs.iter() {
+                let next_token = if *base_token == last_token {
+                    *quote_token
+                } else if *quote_token == last_token {
+                    *base_token
+                } else {
+                    continue;
+                };
+                if path.contains(&next_token) || !self.get_pair_state(*base_token, *quote_token) {
+                    continue;
+                }
+                path.push(next_token);
+                self.extend_route(path, token_out, amount_in, best);
+                path.pop();
+            }
+        }
+
+        ///Last or average price of deposited_token in withdrawn_token, inverting the pair if it is registered the other way
+        fn hop_price_checked(
+            &self,
+            deposited_token: AccountId,
+            withdrawn_token: AccountId,
+            use_average_price: bool,
+            max_age: u64,
+        ) -> Result<Price, OracleDexError> {
+            let pair_price = |base_token, quote_token| match use_average_price {
+                true => self.get_average_price_checked(base_token, quote_token, max_age),
+                false => self.get_pair_price_checked(base_token, quote_token, max_age),
+            };
+            match pair_price(deposited_token, withdrawn_token) {
+                Err(OracleDexError::NoPrice) => pair_price(withdrawn_token, deposited_token)?
+                    .inverse()
+                    .ok_or(OracleDexError::InvalidPrice),
+                price => price,
+            }
+        }
+
+        ///Pair reserves as (deposited_token reserve, withdrawn_token reserve)
+        fn reserves_in_out(
+            &self,
+            pair: (AccountId, AccountId),
+            deposited_is_base: bool,
+        ) -> (Balance, Balance) {
+            let reserves = self.get_pair_reserves(pair.0, pair.1);
+            match deposited_is_base {
+                true => (reserves.base_reserve, reserves.quote_reserve),
+                false => (reserves.quote_reserve, reserves.base_reserve),
+            }
+        }
+
+        fn set_reserves_in_out(
+            &mut self,
+            pair: (AccountId, AccountId),
+            deposited_is_base: bool,
+            reserve_in: Balance,
+            reserve_out: Balance,
+        ) {
+            let reserves = match deposited_is_base {
+                true => PairReserves {
+                    base_reserve: reserve_in,
+                    quote_reserve: reserve_out,
+                },
+                false => PairReserves {
+                    base_reserve: reserve_out,
+                    quote_reserve: reserve_in,
+                },
+            };
+            self.pair_reserves.insert(&pair, &reserves);
+        }
+
+        ///withdrawn_token amount_in is worth at the last or average oracle price, rounded down
+        fn oracle_amount_out(
+            &self,
+            pair: (AccountId, AccountId),
+            deposited_is_base: bool,
+            amount_in: Balance,
+            use_average_price: bool,
+        ) -> Result<Balance, OracleDexError> {
+            let price = match use_average_price {
+                true => self.get_average_price(pair.0, pair.1),
+                false => self.get_pair_price(pair.0, pair.1),
+            };
+            if price.is_zero() {
+                return Err(OracleDexError::NoPrice);
+            }
+            let base_decimals = self.token_decimals(pair.0);
+            let quote_decimals = self.token_decimals(pair.1);
+            match deposited_is_base {
+                true => {
+                    price.quote_amount(amount_in, base_decimals, quote_decimals, Rounding::Down)
+                }
+                false => {
+                    price.base_amount(amount_in, base_decimals, quote_decimals, Rounding::Down)
+                }
+            }
+            .ok_or(OracleDexError::InvalidPrice)
+        }
+
+        ///deposited_token needed for amount_out at the last oracle price, rounded up
+        fn oracle_amount_in(
+            &self,
+            pair: (AccountId, AccountId),
+            deposited_is_base: bool,
+            amount_out: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            let price = self.get_pair_price(pair.0, pair.1);
+            if price.is_zero() {
+                return Err(OracleDexError::NoPrice);
+            }
+            let base_decimals = self.token_decimals(pair.0);
+            let quote_decimals = self.token_decimals(pair.1);
+            match deposited_is_base {
+                true => price.base_amount(amount_out, base_decimals, quote_decimals, Rounding::Up),
+                false => {
+                    price.quote_amount(amount_out, base_decimals, quote_decimals, Rounding::Up)
+                }
+            }
+            .ok_or(OracleDexError::InvalidPrice)
+        }
+
+        ///Shares minted for a deposit of up to base_amount and quote_amount and the amounts actually taken.
+        ///The first provider sets the ratio and gets a share per token deposited,
+        ///later ones get shares of the side they bring the least of and pay both sides in the current ratio, rounded up
+        fn pool_deposit(
+            reserves: &PairReserves,
+            total_shares: Balance,
+            base_amount: Balance,
+            quote_amount: Balance,
+        ) -> Result<(Balance, Balance, Balance), OracleDexError> {
+            let (shares, base_in, quote_in) = match total_shares {
+                0 => (base_amount + quote_amount, base_amount, quote_amount),
+                _ => {
+                    let shares = [
+                        (base_amount, reserves.base_reserve),
+                        (quote_amount, reserves.quote_reserve),
+                    ]
+                    .iter()
+                    .filter(|(_, reserve)| *reserve > 0)
+                    .map(|(amount, reserve)| {
+                        mul_div(*amount, total_shares, *reserve, Rounding::Down).unwrap_or(0)
+                    })
+                    .min()
+                    .ok_or(OracleDexError::EmptyPool)?;
+                    let base_in =
+                        mul_div(shares, reserves.base_reserve, total_shares, Rounding::Up)
+                            .ok_or(OracleDexError::InsufficientShares)?;
+                    let quote_in =
+                        mul_div(shares, reserves.quote_reserve, total_shares, Rounding::Up)
+                            .ok_or(OracleDexError::InsufficientShares)?;
+                    (shares, base_in, quote_in)
+                }
+            };
+            if shares == 0 {
+                return Err(OracleDexError::InsufficientShares);
+            }
+            Ok((shares, base_in, quote_in))
+        }
+
+        ///Reserves paid out for shares, rounded down. Emptied reserves pay nothing, redeeming still clears the shares
+        fn pool_redemption(
+            reserves: &PairReserves,
+            total_shares: Balance,
+            shares: Balance,
+        ) -> (Balance, Balance) {
+            (
+                mul_div(shares, reserves.base_reserve, total_shares, Rounding::Down).unwrap_or(0),
+                mul_div(shares, reserves.quote_reserve, total_shares, Rounding::Down).unwrap_or(0),
+            )
+        }
+
+        ///x * y = k fill of amount_in after the lp fee, rounded down
+        fn constant_product_amount_out(
+            &self,
+            reserve_in: Balance,
+            reserve_out: Balance,
+            amount_in: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            if reserve_in == 0 || reserve_out == 0 {
+                return Err(OracleDexError::InsufficientLiquidity);
+            }
+            let amount_in_after_fee = self.after_lp_fee(amount_in)?;
+            let new_reserve_in = reserve_in
+                .checked_add(amount_in_after_fee)
+                .ok_or(OracleDexError::InsufficientLiquidity)?;
+            mul_div(
+                amount_in_after_fee,
+                reserve_out,
+                new_reserve_in,
+                Rounding::Down,
+            )
+            .ok_or(OracleDexError::InsufficientLiquidity)
+        }
+
+        ///amount_in, lp fee included, a x * y = k fill needs to return amount_out, rounded up
+        fn constant_product_amount_in(
+            &self,
+            reserve_in: Balance,
+            reserve_out: Balance,
+            amount_out: Balance,
+        ) -> Result<Balance, OracleDexError> {
+            if reserve_in == 0 || amount_out >= reserve_out {
+                return Err(OracleDexError::InsufficientLiquidity);
+            }
+            let amount_in_after_fee = mul_div(
+                reserve_in,
+                amount_out,
+                reserve_out - amount_out,
+                Rounding::Up,
+            )
+            .ok_or(OracleDexError::InsufficientLiquidity)?;
+            self.before_lp_fee(amount_in_after_fee)
+        }
+
+        ///Part of amount_in left to be swapped once the lp fee is kept, rounded down
+        fn after_lp_fee(&self, amount_in: Balance) -> Result<Balance, OracleDexError> {
+            mul_div(
+                amount_in,
+                BPS - self.lp_fee_bps as u128,
+                BPS,
+                Rounding::Down,
+            )
+            .ok_or(OracleDexError::InsufficientLiquidity)
+        }
+
+        ///amount_in that leaves amount_in_after_fee once the lp fee is kept, rounded up
+        fn before_lp_fee(&self, amount_in_after_fee: Balance) -> Result<Balance, OracleDexError> {
+            mul_div(
+                amount_in_after_fee,
+                BPS,
+                BPS - self.lp_fee_bps as u128,
+                Rounding::Up,
+            )
+            .ok_or(OracleDexError::InsufficientLiquidity)
+        }
+
+        ///Rejects constant product fills further than max_swap_deviation_bps from the last oracle price.
+        ///Both sides are compared after the lp fee
+        fn check_swap_deviation(
+            &self,
+            pair: (AccountId, AccountId),
+            deposited_is_base: bool,
+            amount_in: Balance,
+            amount_out: Balance,
+        ) -> Result<(), OracleDexError> {
+            let oracle_amount_out = self.oracle_amount_out(
+                pair,
+                deposited_is_base,
+                self.after_lp_fee(amount_in)?,
+                false,
+            )?;
+            let deviation_bps = mul_div(
+                oracle_amount_out.abs_diff(amount_out),
+                BPS,
+                oracle_amount_out,
+                Rounding::Up,
+            )
+            .unwrap_or(u128::MAX);
+            if deviation_bps > self.max_swap_deviation_bps as u128 {
+                return Err(OracleDexError::PriceDeviation);
+            }
+            Ok(())
+        }
+
+        #[cfg(not(test))]
+        fn token_decimals(&self, token: AccountId) -> u8 {
+            use openbrush::contracts::traits::psp22::extensions::metadata::PSP22MetadataRef;
+            PSP22MetadataRef::token_decimals(&token)
+        }
+
+        ///Off-chain tests cannot call token contracts, the tokens they trade all have 12 decimals
+        #[cfg(test)]
+        fn token_decimals(&self, _token: AccountId) -> u8 {
+            12
+        }
+
+        fn approve_token_for_swap(
+            &mut self,
+            withdrawn_token: AccountId,
+            contract_address: AccountId,
+            amount: Balance,
+        ) -> Result<(), OracleDexError> {
+            PSP22Ref::approve_builder(&withdrawn_token, contract_address, amount)
+                .fire()
+                .map_err(|_| OracleDexError::TransferFailed)?
+                .map_err(|_| OracleDexError::TransferFailed)?;
+            Ok(())
+        }
+
+        fn withdraw_to_caller(
+            &mut self,
+            token_address: AccountId,
+            amount: Balance,
+        ) -> Result<(), OracleDexError> {
+            if amount == 0 {
+                return Ok(());
+            }
+            PSP22Ref::transfer_builder(
+                &token_address,
+                self.env().caller(),
+                amount,
+                Vec::<u8>::new(),
+            )
+            .fire()
+            .map_err(|_| OracleDexError::TransferFailed)?
+            .map_err(|_| OracleDexError::TransferFailed)?;
+
+            //SHOULD EMMIT EVENT
+
+            Ok(())
+        }
+
+        fn make_deposit(
+            &mut self,
+            token_address: AccountId,
+            amount: Balance,
+        ) -> Result<(), OracleDexError> {
+            if amount == 0 {
+                return Ok(());
+            }
+            let from_caller = self.env().caller().clone();
+            let contract = self.env().account_id().clone();
+
+            if PSP22Ref::allowance(&token_address, from_caller, contract) < amount {
+                return Err(OracleDexError::InsufficientAllowance);
+            }
+
+            PSP22Ref::transfer_from_builder(
+                &token_address,
+                from_caller,
+                contract,
+                amount,
+                Vec::<u8>::new(),
+            )
+            .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .map_err(|_| OracleDexError::TransferFailed)?
+            .map_err(|_| OracleDexError::TransferFailed)?;
 
             //SHOULD EMMIT EVENT
 
             Ok(())
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{test, DefaultEnvironment};
+        use ink_lang as ink;
+
+        fn base() -> AccountId {
+            AccountId::from([0x01; 32])
+        }
+
+        fn quote() -> AccountId {
+            AccountId::from([0x02; 32])
+        }
+
+        fn now() -> u64 {
+            ink_env::block_timestamp::<DefaultEnvironment>()
+        }
+
+        ///Advance blocks until the block timestamp is past timestamp
+        fn advance_past(timestamp: u64) {
+            while now() <= timestamp {
+                test::advance_block::<DefaultEnvironment>();
+            }
+        }
+
+        ///Price of whole quote tokens per base token at PRICE_SCALE
+        fn price(units: u128) -> Price {
+            Price::new(units * PRICE_SCALE, PRICE_SCALE)
+        }
+
+        ///alice deploys and administers with the base/quote pair active. Scheduled changes apply at once
+        fn new_oracle() -> (Oracle, test::DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let mut oracle = Oracle::new();
+            oracle.timelock.min_delay = 0;
+            assert_eq!(oracle.register_pool(base()), Ok(()));
+            assert_eq!(oracle.register_pool(quote()), Ok(()));
+            oracle._activate_pair(base(), quote());
+            (oracle, accounts)
+        }
+
+        fn execute_scheduled(oracle: &mut Oracle) {
+            for id in oracle.get_scheduled_change_ids() {
+                assert_eq!(oracle.execute_change(id), Ok(()));
+            }
+        }
+
+        ///alice whitelists reporters next to herself and sets the round params
+        fn set_reporters(
+            oracle: &mut Oracle,
+            reporters: &[AccountId],
+            round_window: u64,
+            quorum: u8,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            for reporter in reporters {
+                assert_eq!(oracle.add_reporter(*reporter), Ok(()));
+            }
+            execute_scheduled(oracle);
+            assert_eq!(oracle.set_round_params(round_window, quorum, 500), Ok(()));
+            execute_scheduled(oracle);
+        }
+
+        fn submit(
+            oracle: &mut Oracle,
+            reporter: AccountId,
+            units: u128,
+        ) -> Result<(), OracleDexError> {
+            test::set_caller::<DefaultEnvironment>(reporter);
+            oracle.update_price(base(), quote(), price(units))
+        }
+
+        fn stats(submissions: u32, missed_rounds: u32, outliers: u32) -> ReporterStats {
+            ReporterStats {
+                submissions,
+                missed_rounds,
+                outliers,
+            }
+        }
+
+        #[ink::test]
+        fn median_is_the_middle_submission_or_the_floored_mean_of_the_two_middle_ones() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let submissions = |mantissas: &[u128]| -> Vec<(AccountId, u128)> {
+                mantissas
+                    .iter()
+                    .map(|mantissa| (accounts.alice, *mantissa))
+                    .collect()
+            };
+
+            assert_eq!(Oracle::median(&submissions(&[300, 100, 200])), 200);
+            assert_eq!(Oracle::median(&submissions(&[7])), 7);
+            assert_eq!(Oracle::median(&submissions(&[5, 7])), 6);
+            assert_eq!(Oracle::median(&submissions(&[4, 3])), 3);
+            assert_eq!(Oracle::median(&submissions(&[9, 1, 4, 5])), 4);
+            assert_eq!(
+                Oracle::median(&submissions(&[u128::MAX, u128::MAX])),
+                u128::MAX
+            );
+            assert_eq!(
+                Oracle::median(&submissions(&[u128::MAX, u128::MAX - 1])),
+                u128::MAX - 1
+            );
+        }
+
+        #[ink::test]
+        fn quorum_publishes_the_median_and_flags_outliers() {
+            let (mut oracle, accounts) = new_oracle();
+            set_reporters(
+                &mut oracle,
+                &[accounts.bob, accounts.charlie, accounts.django],
+                10_000,
+                3,
+            );
+
+            assert_eq!(submit(&mut oracle, accounts.alice, 100), Ok(()));
+            assert_eq!(submit(&mut oracle, accounts.bob, 102), Ok(()));
+            assert_eq!(oracle.get_pair_price_data(base(), quote()), None);
+            assert_eq!(submit(&mut oracle, accounts.charlie, 300), Ok(()));
+            assert_eq!(oracle.get_pair_price(base(), quote()), price(102));
+
+            //a late submission within the window is checked against the published median
+            assert_eq!(submit(&mut oracle, accounts.django, 150), Ok(()));
+            assert_eq!(
+                submit(&mut oracle, accounts.bob, 102),
+                Err(OracleDexError::AlreadySubmitted)
+            );
+            assert_eq!(oracle.get_pair_price(base(), quote()), price(102));
+
+            let round = oracle.get_price_round(base(), quote()).unwrap();
+            assert_eq!(round.id, 0);
+            assert!(round.finalized);
+            assert_eq!(round.submissions.len(), 4);
+            assert_eq!(oracle.get_reporter_stats(accounts.alice), stats(1, 0, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.bob), stats(1, 0, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.charlie), stats(1, 0, 1));
+            assert_eq!(oracle.get_reporter_stats(accounts.django), stats(1, 0, 1));
+        }
+
+        #[ink::test]
+        fn expired_rounds_count_a_miss_for_every_reporter_that_did_not_submit() {
+            let (mut oracle, accounts) = new_oracle();
+            set_reporters(&mut oracle, &[accounts.bob, accounts.charlie], 1_000, 2);
+
+            let first_round = now();
+            assert_eq!(submit(&mut oracle, accounts.alice, 100), Ok(()));
+            advance_past(first_round + 1_000);
+
+            //bob's submission closes the round alice submitted to alone, nothing was published
+            assert_eq!(submit(&mut oracle, accounts.bob, 100), Ok(()));
+            assert_eq!(oracle.get_pair_price_data(base(), quote()), None);
+            let second_round = now();
+            assert_eq!(submit(&mut oracle, accounts.alice, 110), Ok(()));
+            assert_eq!(oracle.get_pair_price(base(), quote()), price(105));
+            let round = oracle.get_price_round(base(), quote()).unwrap();
+            assert_eq!((round.id, round.started_at), (1, second_round));
+            assert!(round.finalized);
+
+            assert_eq!(oracle.get_reporter_stats(accounts.alice), stats(2, 0, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.bob), stats(1, 1, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.charlie), stats(0, 1, 0));
+
+            advance_past(second_round + 1_000);
+            assert_eq!(submit(&mut oracle, accounts.charlie, 100), Ok(()));
+            assert_eq!(oracle.get_price_round(base(), quote()).unwrap().id, 2);
+            assert_eq!(oracle.get_reporter_stats(accounts.alice), stats(2, 0, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.bob), stats(1, 1, 0));
+            assert_eq!(oracle.get_reporter_stats(accounts.charlie), stats(1, 2, 0));
+        }
+
+        #[ink::test]
+        fn only_whitelisted_reporters_submit_and_they_can_always_reach_the_quorum() {
+            let (mut oracle, accounts) = new_oracle();
+
+            assert_eq!(
+                submit(&mut oracle, accounts.bob, 100),
+                Err(OracleDexError::AccessControlError(
+                    AccessControlError::MissingRole
+                ))
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                oracle.set_round_params(1_000, 2, 500),
+                Err(OracleDexError::InvalidRoundParams)
+            );
+            assert_eq!(
+                oracle.set_round_params(1_000, 0, 500),
+                Err(OracleDexError::InvalidRoundParams)
+            );
+            assert_eq!(
+                oracle.set_round_params(0, 1, 500),
+                Err(OracleDexError::InvalidRoundParams)
+            );
+
+            set_reporters(&mut oracle, &[accounts.bob], 1_000, 1);
+            assert_eq!(oracle.get_reporters(), vec![accounts.alice, accounts.bob]);
+            assert_eq!(submit(&mut oracle, accounts.bob, 100), Ok(()));
+
+            //removing bob was fine when scheduled but no longer is once the quorum was raised to 2
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(oracle.remove_reporter(accounts.bob), Ok(()));
+            let remove_bob = *oracle.get_scheduled_change_ids().last().unwrap();
+            assert_eq!(oracle.set_round_params(1_000, 2, 500), Ok(()));
+            let raise_quorum = *oracle.get_scheduled_change_ids().last().unwrap();
+            assert_eq!(oracle.execute_change(raise_quorum), Ok(()));
+            assert_eq!(
+                oracle.execute_change(remove_bob),
+                Err(OracleDexError::InvalidRoundParams)
+            );
+            assert_eq!(oracle.get_scheduled_change_ids(), vec![remove_bob]);
+            assert_eq!(
+                oracle.remove_reporter(accounts.bob),
+                Err(OracleDexError::InvalidRoundParams)
+            );
+
+            assert_eq!(oracle.set_round_params(1_000, 1, 500), Ok(()));
+            let lower_quorum = *oracle.get_scheduled_change_ids().last().unwrap();
+            assert_eq!(oracle.execute_change(lower_quorum), Ok(()));
+            assert_eq!(oracle.execute_change(remove_bob), Ok(()));
+            assert_eq!(oracle.get_reporters(), vec![accounts.alice]);
+            assert_eq!(
+                submit(&mut oracle, accounts.bob, 100),
+                Err(OracleDexError::AccessControlError(
+                    AccessControlError::MissingRole
+                ))
+            );
+        }
+
+        fn observation(timestamp: u64, cumulative_price: u128, price: u128) -> PriceObservation {
+            PriceObservation {
+                timestamp,
+                cumulative_price,
+                price,
+            }
+        }
+
+        #[ink::test]
+        fn cumulative_price_extrapolates_the_last_observation_at_or_before_timestamp() {
+            let observations = vec![observation(1_000, 0, 100), observation(1_600, 60_000, 300)];
+
+            for (timestamp, cumulative_price) in [
+                (999, 0),
+                (1_000, 0),
+                (1_300, 30_000),
+                (1_600, 60_000),
+                (2_000, 180_000),
+            ] {
+                assert_eq!(
+                    Oracle::cumulative_price_at(&observations, timestamp),
+                    cumulative_price
+                );
+            }
+            assert_eq!(Oracle::cumulative_price_at(&Vec::new(), 1_000), 0);
+            //only differences count, so wrapping past u128::MAX is harmless
+            assert_eq!(
+                Oracle::cumulative_price_at(&vec![observation(0, u128::MAX, 2)], 1),
+                1
+            );
+        }
+
+        #[ink::test]
+        fn twap_weighs_every_published_price_by_how_long_it_held() {
+            let (mut oracle, _) = new_oracle();
+            assert_eq!(
+                oracle.get_twap(base(), quote(), 1_000),
+                Price::new(0, PRICE_SCALE)
+            );
+            assert_eq!(
+                oracle.get_twap_checked(base(), quote(), 1_000, 1_000),
+                Err(OracleDexError::NoPrice)
+            );
+
+            let first = now();
+            oracle.publish_price(base(), quote(), Price::new(100, PRICE_SCALE));
+            advance_past(first + 1_000);
+            let second = now();
+            //a later price in the same block replaces the earlier one
+            oracle.publish_price(base(), quote(), Price::new(200, PRICE_SCALE));
+            oracle.publish_price(base(), quote(), Price::new(300, PRICE_SCALE));
+            assert_eq!(
+                oracle.get_price_observations(base(), quote()),
+                vec![
+                    observation(first, 0, 100),
+                    observation(second, 100 * (second - first) as u128, 300),
+                ]
+            );
+
+            advance_past(second + 1_000);
+            let until = now();
+            let twap =
+                |oracle: &Oracle, window: u64| oracle.get_twap(base(), quote(), window).mantissa;
+            let first_held = (second - first) as u128;
+            let second_held = (until - second) as u128;
+
+            assert_eq!(twap(&oracle, until - second), 300);
+            assert_eq!(
+                twap(&oracle, until - second + 200),
+                (100 * 200 + 300 * second_held) / (200 + second_held)
+            );
+            //windows reaching before the first observation are shortened to it
+            for window in [until - first, until - first + 1, u64::MAX] {
+                assert_eq!(
+                    twap(&oracle, window),
+                    (100 * first_held + 300 * second_held) / (first_held + second_held)
+                );
+            }
+            assert_eq!(twap(&oracle, 0), 300);
+
+            //the twap is as fresh as the last published price
+            assert_eq!(
+                oracle.get_twap_checked(base(), quote(), 1_000, until - second),
+                Ok(oracle.get_twap(base(), quote(), 1_000))
+            );
+            assert_eq!(
+                oracle.get_twap_checked(base(), quote(), 1_000, until - second - 1),
+                Err(OracleDexError::StalePrice)
+            );
+        }
+
+        #[ink::test]
+        fn observations_beyond_the_cap_drop_the_oldest() {
+            let (mut oracle, _) = new_oracle();
+            let first = now();
+            for units in 1..=(MAX_PRICE_OBSERVATIONS as u128 + 1) {
+                oracle.publish_price(base(), quote(), Price::new(units, PRICE_SCALE));
+                test::advance_block::<DefaultEnvironment>();
+            }
+
+            let observations = oracle.get_price_observations(base(), quote());
+            assert_eq!(observations.len(), MAX_PRICE_OBSERVATIONS as usize);
+            assert!(observations[0].timestamp > first);
+            assert_eq!(observations[0].price, 2);
+            //the longest twap only reaches back to the oldest observation kept
+            let oldest = observations[0].timestamp;
+            let span = (now() - oldest) as u128;
+            assert_eq!(
+                oracle.get_twap(base(), quote(), u64::MAX).mantissa,
+                Oracle::cumulative_price_at(&observations, now())
+                    .wrapping_sub(observations[0].cumulative_price)
+                    / span
+            );
+        }
+
+        fn reserves(base_reserve: Balance, quote_reserve: Balance) -> PairReserves {
+            PairReserves {
+                base_reserve,
+                quote_reserve,
+            }
+        }
+
+        #[ink::test]
+        fn first_deposit_mints_a_share_per_token_and_sets_the_ratio() {
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(0, 0), 0, 1_000, 4_000),
+                Ok((5_000, 1_000, 4_000))
+            );
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(0, 0), 0, 0, 0),
+                Err(OracleDexError::InsufficientShares)
+            );
+        }
+
+        #[ink::test]
+        fn later_deposits_mint_for_the_scarcer_side_and_round_amounts_in_up() {
+            //quote is brought in excess, only what matches the base side in the pool's ratio is taken
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(1_000, 4_000), 5_000, 100, 1_000),
+                Ok((500, 100, 400))
+            );
+            //3 shares of 10 are worth 0.9 base and 2.1 quote, the provider pays 1 and 3
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(3, 7), 10, 1, 10),
+                Ok((3, 1, 3))
+            );
+            //a side the pool holds none of does not limit the shares
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(0, 100), 10, 5, 50),
+                Ok((5, 0, 50))
+            );
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(1_000, 1_000), 10, 50, 50),
+                Err(OracleDexError::InsufficientShares)
+            );
+            assert_eq!(
+                Oracle::pool_deposit(&reserves(0, 0), 10, 50, 50),
+                Err(OracleDexError::EmptyPool)
+            );
+        }
+
+        #[ink::test]
+        fn redemptions_pay_the_shares_part_of_the_reserves_rounded_down() {
+            assert_eq!(
+                Oracle::pool_redemption(&reserves(101, 403), 10, 3),
+                (30, 120)
+            );
+            assert_eq!(
+                Oracle::pool_redemption(&reserves(101, 403), 10, 10),
+                (101, 403)
+            );
+            assert_eq!(Oracle::pool_redemption(&reserves(0, 0), 10, 10), (0, 0));
+
+            //what rounding leaves behind stays with the remaining providers
+            let (shares, base_in, quote_in) =
+                Oracle::pool_deposit(&reserves(3, 7), 10, 1, 10).unwrap();
+            let (base_out, quote_out) =
+                Oracle::pool_redemption(&reserves(3 + base_in, 7 + quote_in), 10 + shares, shares);
+            assert!(base_out <= base_in && quote_out <= quote_in);
+        }
+
+        #[ink::test]
+        fn liquidity_is_refused_for_unregistered_pools_and_inactive_pairs() {
+            let (mut oracle, accounts) = new_oracle();
+            let unregistered = AccountId::from([0x03; 32]);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                oracle.add_pool_liquidity(base(), unregistered, 100, 100),
+                Err(OracleDexError::PoolNotRegistered)
+            );
+            assert_eq!(
+                oracle.add_pool_liquidity(quote(), base(), 100, 100),
+                Err(OracleDexError::PairNotActive)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(oracle.pause_pair(base(), quote()), Ok(()));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                oracle.add_pool_liquidity(base(), quote(), 100, 100),
+                Err(OracleDexError::PairNotActive)
+            );
+        }
+
+        #[ink::test]
+        fn shares_of_an_emptied_pool_are_still_redeemed_and_cleared() {
+            let (mut oracle, accounts) = new_oracle();
+            let pair = (base(), quote());
+            let (shares, total_shares): (Balance, Balance) = (30, 40);
+            oracle.pool_shares.insert(&(pair, accounts.bob), &shares);
+            oracle.pool_total_shares.insert(&pair, &total_shares);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                oracle.withdraw_pool_liquidity(base(), quote(), 31),
+                Err(OracleDexError::InsufficientShares)
+            );
+            assert_eq!(
+                oracle.withdraw_pool_liquidity(base(), quote(), 0),
+                Err(OracleDexError::InsufficientShares)
+            );
+            assert_eq!(
+                oracle.withdraw_pool_liquidity(base(), quote(), 30),
+                Ok((0, 0))
+            );
+            assert_eq!(oracle.get_pool_shares(base(), quote(), accounts.bob), 0);
+            assert_eq!(oracle.get_pool_total_shares(base(), quote()), 10);
+        }
+
+        fn token(byte: u8) -> AccountId {
+            AccountId::from([byte; 32])
+        }
+
+        ///base/quote at 2, quote/0x03 at 3 and base/0x03 at 5, each pair holding 1_000_000 of both tokens.
+        ///0x04 has a pool but no pair
+        fn new_routed_oracle() -> Oracle {
+            let (mut oracle, _) = new_oracle();
+            for byte in [0x03, 0x04] {
+                assert_eq!(oracle.register_pool(token(byte)), Ok(()));
+            }
+            for (base_token, quote_token, units) in [
+                (base(), quote(), 2),
+                (quote(), token(0x03), 3),
+                (base(), token(0x03), 5),
+            ] {
+                oracle._activate_pair(base_token, quote_token);
+                oracle.publish_price(base_token, quote_token, price(units));
+                oracle
+                    .pair_reserves
+                    .insert(&(base_token, quote_token), &reserves(1_000_000, 1_000_000));
+            }
+            oracle
+        }
+
+        #[ink::test]
+        fn path_amounts_chain_every_hop_after_its_lp_fee() {
+            let oracle = new_routed_oracle();
+
+            //0.3% of every hop stays in its pair, then base and quote sell at their pair's price
+            assert_eq!(
+                oracle.path_amounts_out(&vec![base(), quote(), token(0x03)], 10_000),
+                Ok(vec![10_000, 19_940, 59_640])
+            );
+            //against the registered direction the price is inverted
+            assert_eq!(
+                oracle.path_amounts_out(&vec![token(0x03), quote(), base()], 60_000),
+                Ok(vec![60_000, 19_940, 9_940])
+            );
+            assert_eq!(
+                oracle.get_amount_out_path(vec![base(), quote(), token(0x03)], 10_000),
+                Ok(59_640)
+            );
+        }
+
+        #[ink::test]
+        fn paths_must_be_short_free_of_repeats_and_made_of_active_pairs() {
+            let oracle = new_routed_oracle();
+
+            for path in [
+                vec![base()],
+                vec![base(), quote(), base()],
+                vec![base(), quote(), token(0x03), token(0x04), token(0x05)],
+            ] {
+                assert_eq!(
+                    oracle.path_amounts_out(&path, 10_000),
+                    Err(OracleDexError::InvalidPath)
+                );
+            }
+            assert_eq!(
+                oracle.path_amounts_out(&vec![base(), token(0x04)], 10_000),
+                Err(OracleDexError::PairNotActive)
+            );
+        }
+
+        #[ink::test]
+        fn best_route_picks_the_largest_fill_among_the_routes_that_can_be_filled() {
+            let mut oracle = new_routed_oracle();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(
+                oracle.get_best_route(base(), token(0x03), 10_000),
+                Some((vec![base(), quote(), token(0x03)], 59_640))
+            );
+            assert_eq!(oracle.get_best_route(base(), token(0x04), 10_000), None);
+
+            //the two hop route no longer has the 0x03 to pay out, the direct pair still does
+            oracle
+                .pair_reserves
+                .insert(&(quote(), token(0x03)), &reserves(1_000_000, 50_000));
+            assert_eq!(
+                oracle.path_amounts_out(&vec![base(), quote(), token(0x03)], 10_000),
+                Err(OracleDexError::InsufficientLiquidity)
+            );
+            assert_eq!(
+                oracle.get_best_route(base(), token(0x03), 10_000),
+                Some((vec![base(), token(0x03)], 49_850))
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(oracle.pause_pair(base(), token(0x03)), Ok(()));
+            assert_eq!(oracle.get_best_route(base(), token(0x03), 10_000), None);
+        }
+
+        #[ink::test]
+        fn constant_product_hops_are_bounded_by_the_oracle_price() {
+            let mut oracle = new_routed_oracle();
+            oracle
+                .pricing_modes
+                .insert(&(base(), quote()), &PricingMode::ConstantProduct);
+            oracle
+                .pair_reserves
+                .insert(&(base(), quote()), &reserves(1_000_000, 2_000_000));
+
+            //997 left after the fee fills 997 * 2_000_000 / 1_000_997 quote, within 0.11% of the oracle price
+            assert_eq!(
+                oracle.path_amounts_out(&vec![base(), quote(), token(0x03)], 1_000),
+                Ok(vec![1_000, 1_992, 5_958])
+            );
+            //181_322 out is 9.07% short of the 199_400 the oracle price gives, above max_swap_deviation_bps
+            assert_eq!(
+                oracle.path_amounts_out(&vec![base(), quote()], 100_000),
+                Err(OracleDexError::PriceDeviation)
+            );
+        }
+    }
 }
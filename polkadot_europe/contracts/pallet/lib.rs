@@ -28,6 +28,31 @@ mod pallet {
         factory_addr: AccountId,
         treasury_manager_addr: AccountId,
         treasury_token_address: AccountId,
+        pending_admin: Option<PendingHandover>,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_proposed {
+        #[ink(topic)]
+        role: RoleType,
+        from: AccountId,
+        to: AccountId,
+        deadline: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_accepted {
+        #[ink(topic)]
+        role: RoleType,
+        from: AccountId,
+        to: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_cancelled {
+        #[ink(topic)]
+        role: RoleType,
+        to: AccountId,
     }
 
     const ADMIN: RoleType = ink_lang::selector_id!("ADMIN");
@@ -48,6 +73,7 @@ mod pallet {
                 instance.factory_addr = factory_address;
                 instance.treasury_manager_addr = Default::default();
                 instance.treasury_token_address = treasury_token_address;
+                instance.pending_admin = None;
             })
         }
 
@@ -66,22 +92,73 @@ mod pallet {
             self.admin
         }
 
+        ///account becomes ADMIN here once it calls accept_admin and MANAGER of the treasury manager
+        ///once it calls accept_manager there, both within HANDOVER_WINDOW
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        pub fn set_admin_here_and_manager_for_tm(
+        pub fn propose_admin_here_and_manager_for_tm(
             &mut self,
             account: AccountId,
-        ) -> Result<(), AccessControlError> {
-            self.set_treasury_contract_manager(account);
+        ) -> Result<(), TreasuryManagerError> {
+            self.propose_treasury_contract_manager(account)?;
+            self.propose_admin(account)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn propose_admin(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            let deadline = self.env().block_timestamp() + HANDOVER_WINDOW;
+            self.pending_admin = Some(PendingHandover { account, deadline });
+            self.env().emit_event(ev_handover_proposed {
+                role: ADMIN,
+                from: self.admin,
+                to: account,
+                deadline,
+            });
+            Ok(())
+        }
 
-            self.renounce_role(ADMIN, self.admin);
-            self.grant_role(ADMIN, account)
-                .expect("Should grant admin's role");
+        ///Called by the proposed account. The previous ADMIN loses the role
+        #[ink(message)]
+        pub fn accept_admin(&mut self) -> Result<(), TreasuryManagerError> {
+            let caller = self.env().caller();
+            self.pending_admin
+                .ok_or(TreasuryManagerError::NoPendingHandover)?
+                .check_acceptance(caller, self.env().block_timestamp())?;
+
+            let previous_admin = self.admin;
+            self._do_revoke_role(ADMIN, previous_admin);
+            self._setup_role(ADMIN, caller);
+            self.admin = caller;
+            self.pending_admin = None;
+
+            self.env().emit_event(ev_handover_accepted {
+                role: ADMIN,
+                from: previous_admin,
+                to: caller,
+            });
+            Ok(())
+        }
 
-            self.admin = account;
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn cancel_admin_handover(&mut self) -> Result<(), TreasuryManagerError> {
+            let pending = self
+                .pending_admin
+                .take()
+                .ok_or(TreasuryManagerError::NoPendingHandover)?;
+            self.env().emit_event(ev_handover_cancelled {
+                role: ADMIN,
+                to: pending.account,
+            });
             Ok(())
         }
 
+        #[ink(message)]
+        pub fn get_pending_admin(&self) -> Option<PendingHandover> {
+            self.pending_admin
+        }
+
         // *** FACTORY ***/
         ///Launch new treasury_manager
         #[ink(message, payable)]
@@ -142,8 +219,10 @@ mod pallet {
             requested_value: Balance,
             payment_type: PaymentType,
             payment_schedule: Vec<u64>,
+            payment_plan: PaymentPlan,
             payee_accounts: Vec<AccountId>,
-        ) -> Result<(), AccessControlError> {
+            payee_shares: Vec<u16>,
+        ) -> Result<(), TreasuryManagerError> {
             TreasureManagerRef::add_job(
                 &self.treasury_manager_addr,
                 title,
@@ -154,51 +233,127 @@ mod pallet {
                 requested_value,
                 payment_type,
                 payment_schedule,
+                payment_plan,
                 payee_accounts,
-            );
+                payee_shares,
+            )
+        }
 
-            Ok(())
+        ///account accepts on the treasury manager itself
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn propose_treasury_contract_manager(
+            &mut self,
+            account: AccountId,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::propose_operation(
+                &self.treasury_manager_addr,
+                Operation::ProposeManager { account },
+            )
+            .map(|_| ())
         }
 
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        pub fn set_treasury_contract_manager(
+        pub fn cancel_treasury_contract_manager_handover(
+            &mut self,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::cancel_manager_handover(&self.treasury_manager_addr)
+        }
+
+        ///Hand this pallet's ADMIN role on the treasury manager to account, which accepts there
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn propose_treasury_contract_admin(
             &mut self,
             account: AccountId,
-        ) -> Result<(), AccessControlError> {
-            TreasureManagerRef::set_manager(&self.treasury_manager_addr, account);
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::propose_operation(
+                &self.treasury_manager_addr,
+                Operation::ProposeAdmin { account },
+            )
+            .map(|_| ())
+        }
 
-            Ok(())
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn cancel_treasury_contract_admin_handover(
+            &mut self,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::cancel_admin_handover(&self.treasury_manager_addr)
         }
 
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        pub fn remove_job(&mut self, id: u32) -> Result<(), AccessControlError> {
-            TreasureManagerRef::remove_job_info(&self.treasury_manager_addr, id);
+        pub fn cancel_job(&mut self, id: u32, reason: String) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::cancel_job(&self.treasury_manager_addr, id, reason)
+        }
 
-            Ok(())
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn amend_job(
+            &mut self,
+            id: u32,
+            requested_value: Balance,
+            payment_schedule: Vec<u64>,
+            payment_plan: PaymentPlan,
+            payee_accounts: Vec<AccountId>,
+            payee_shares: Vec<u16>,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::amend_job(
+                &self.treasury_manager_addr,
+                id,
+                requested_value,
+                payment_schedule,
+                payment_plan,
+                payee_accounts,
+                payee_shares,
+            )
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn pause_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::pause_job(&self.treasury_manager_addr, id)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn resume_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::resume_job(&self.treasury_manager_addr, id)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn remove_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::remove_job_info(&self.treasury_manager_addr, id)
         }
 
+        ///Funds arrive here once the treasury manager's approvers reach their threshold
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
         pub fn withdraw_funds_from_treasury_manager(
             &mut self,
             amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            TreasureManagerRef::admin_withdrawal(&self.treasury_manager_addr, amount);
-
-            PSP22Ref::transfer_from_builder(
-                &self.treasury_token_address,
-                self.treasury_manager_addr,
-                self.env().account_id(),
-                amount,
-                Vec::<u8>::new(),
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::propose_operation(
+                &self.treasury_manager_addr,
+                Operation::AdminWithdrawal {
+                    amount,
+                    to: self.env().account_id(),
+                },
             )
-            .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
-            .fire()
-            .unwrap();
+            .map(|_| ())
+        }
 
-            Ok(())
+        ///Approve an operation proposed on the treasury manager by another approver
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn approve_treasury_manager_operation(
+            &mut self,
+            hash: OperationHash,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::approve_operation(&self.treasury_manager_addr, hash)
         }
 
         #[ink(message)]
@@ -206,25 +361,41 @@ mod pallet {
         pub fn deposit_funds_to_treasury_manager(
             &mut self,
             amount: Balance,
-        ) -> Result<(), AccessControlError> {
+        ) -> Result<(), TreasuryManagerError> {
             //APPROVE FIRST
             PSP22Ref::approve(
                 &self.treasury_token_address,
                 self.treasury_manager_addr,
                 amount,
-            )
-            .expect("Approval for depositing treasury_token did not go as planned");
+            )?;
 
-            TreasureManagerRef::make_deposit(&self.treasury_manager_addr, amount);
+            TreasureManagerRef::make_deposit(&self.treasury_manager_addr, amount)
+        }
 
-            Ok(())
+        ///Freeze the treasury manager's payments, swaps and job transitions, e.g. when its oracle is compromised
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn pause_treasury_manager(
+            &mut self,
+            reason: String,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::pause(&self.treasury_manager_addr, reason)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        pub fn unpause_treasury_manager(
+            &mut self,
+            reason: String,
+        ) -> Result<(), TreasuryManagerError> {
+            TreasureManagerRef::unpause(&self.treasury_manager_addr, reason)
         }
 
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        pub fn terminate_treasury_manager(&mut self) -> Result<(), AccessControlError> {
+        pub fn terminate_treasury_manager(&mut self) -> Result<(), TreasuryManagerError> {
             //COMMENT OUT THIS FOR HACKATHON TO AVOID ACCIDENTS
-            // TreasureManagerRef::terminate_me(&self.treasury_manager_addr);
+            // TreasureManagerRef::propose_operation(&self.treasury_manager_addr, Operation::TerminateMe);
 
             Ok(())
         }
@@ -9,6 +9,8 @@ pub mod treasury_manager {
     use ink_lang::codegen::Env;
     use ink_primitives::KeyPtr;
 
+    use polkadot_europe::impls::roles::*;
+    use polkadot_europe::impls::timelock::{self, *};
     use polkadot_europe::traits::oracle_dex::*;
     use polkadot_europe::traits::tr_manager::*;
 
@@ -16,21 +18,17 @@ pub mod treasury_manager {
     use ink_storage::traits::{PackedLayout, SpreadLayout};
     use ink_storage::traits::{SpreadAllocate, StorageLayout};
     use openbrush::{
-        contracts::{access_control::*, traits::errors::PSP22Error, traits::psp22::PSP22Ref},
+        contracts::{
+            access_control::*,
+            pausable::*,
+            traits::errors::PSP22Error,
+            traits::psp22::{extensions::metadata::PSP22MetadataRef, PSP22Ref},
+        },
         modifiers,
         storage::Mapping,
         traits::{Storage, String},
     };
 
-    #[derive(
-        Debug, PartialEq, Eq, scale::Encode, scale::Decode, Clone, SpreadLayout, PackedLayout,
-    )]
-    #[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
-    pub enum MoveJobs {
-        OpenToPending,
-        PendingToCompleted,
-    }
-
     // #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     // #[cfg_attr(feature = "std", derive(::scale_info::TypeInfo))]
     // pub enum Error {
@@ -38,6 +36,38 @@ pub mod treasury_manager {
     //     CallerIsNotRecipient,
     // }
 
+    ///Configuration changes held by the timelock, scale encoded in their ScheduledChange
+    #[derive(Debug, scale::Encode, scale::Decode)]
+    enum TreasuryChange {
+        SetOracleDexAddress {
+            oracle_dex_address: AccountId,
+        },
+        SetLiabilityHorizons {
+            liability_horizons: Vec<LiabilityHorizon>,
+        },
+        SetPaymentRoute {
+            token: AccountId,
+            path: Vec<AccountId>,
+        },
+        SetMaxPriceAge {
+            max_price_age: u64,
+        },
+        SetSwapToleranceBps {
+            swap_tolerance_bps: u16,
+        },
+        SetMinDelay {
+            min_delay: u64,
+        },
+    }
+
+    ///Payment queues, in the order of payment_queue_cursors
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    enum PaymentQueue {
+        Native = 0,
+        NativeUsd = 1,
+        NonNative = 2,
+    }
+
     #[derive(
         Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq,
     )]
@@ -52,33 +82,53 @@ pub mod treasury_manager {
         requested_value: Balance,
         payment_type: PaymentType,
         payment_schedule: Vec<u64>,
+        instalment_amounts: Vec<Balance>, //amount due at each payment_schedule timestamp
+        cliff: Option<u64>,
         payee_accounts: Vec<AccountId>,
+        payee_shares: Vec<u16>, //basis points per payee adding up to PAYEE_SHARES_TOTAL
+        payee_amounts_due: Vec<Balance>, //accrued and not yet paid per payee in the job's denomination
+        //per payee, requested_token already swapped for a due whose transfer failed and the treasury tokens it cost
+        payee_amounts_swapped: Vec<(Balance, Balance)>,
+        total_paid: Balance, //paid out so far in the job's denomination
         next_installment_pointer: u32,
-        position_in_vec: u32,
+        streamed_amount: Balance, //part of a Streaming job already claimed
+        status: JobStatus,
+        position_in_vec: u32, //position in the ids vector of its status
+        payment_retries: u8,
+        last_error_code: u8, //0: no error otherwise TreasuryManagerError::code()
+        cancellation_reason: String,
+    }
+
+    ///What is owed in one foreign token within a horizon. in_treasury is its treasury token equivalent
+    #[derive(
+        Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq,
+    )]
+    #[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+    pub struct TokenLiability {
+        token: AccountId,
+        amount: Balance,
+        in_treasury: Balance,
     }
 
+    ///Liabilities of one LiabilityHorizon at the time of the last calculate_liabilities
     #[derive(
-        Default,
-        Debug,
-        PartialEq,
-        Eq,
-        scale::Encode,
-        scale::Decode,
-        Clone,
-        SpreadLayout,
-        PackedLayout,
+        Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq,
     )]
     #[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
-    pub enum UpdateJobVecs {
-        #[default]
-        OpenToPending,
-        PendingToCompleted,
+    pub struct LiabilityReport {
+        name: String,
+        duration: u64,
+        liability_in_treasury: Balance, //payments in treasury tokens e.g. DOT
+        foreign_liabilities: Vec<TokenLiability>, //one per requested token, USD valued payments under USDT
+        total_treasury_liability: Balance,
+        health: u8, //0: critical 1: warning 2: healthy
+        top_up_amount: Balance,
     }
 
     #[ink(event)]
     pub struct liability_threshold_breached_top {
         #[ink(topic)]
-        level_type: u8, //0: ALL 2: 2D 7: 7D 30: 30D
+        horizon_duration: u64, //LiabilityHorizon duration, u64::MAX for all payments
         #[ink(topic)]
         current_balance: Balance,
         #[ink(topic)]
@@ -88,7 +138,7 @@ pub mod treasury_manager {
     #[ink(event)]
     pub struct liability_threshold_breached_med {
         #[ink(topic)]
-        level_type: u8, //0: ALL 2: 2D 7: 7D 30: 30D
+        horizon_duration: u64, //LiabilityHorizon duration, u64::MAX for all payments
         #[ink(topic)]
         current_balance: Balance,
         #[ink(topic)]
@@ -125,49 +175,353 @@ pub mod treasury_manager {
         amount: Balance,
     }
 
+    #[ink(event)]
+    pub struct ev_job_cancelled {
+        #[ink(topic)]
+        job_id: u32,
+        reason: String,
+        clawed_back: Balance, //unpaid amount in the job's denomination that stays in the treasury
+    }
+
+    #[ink(event)]
+    pub struct ev_job_amended {
+        #[ink(topic)]
+        job_id: u32,
+        requested_value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ev_operation_proposed {
+        #[ink(topic)]
+        hash: OperationHash,
+        proposer: AccountId,
+        operation: Operation,
+        expires_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_operation_approved {
+        #[ink(topic)]
+        hash: OperationHash,
+        approver: AccountId,
+        approvals: u8,
+    }
+
+    #[ink(event)]
+    pub struct ev_operation_executed {
+        #[ink(topic)]
+        hash: OperationHash,
+    }
+
+    #[ink(event)]
+    pub struct ev_operation_expired {
+        #[ink(topic)]
+        hash: OperationHash,
+    }
+
+    #[ink(event)]
+    pub struct ev_paused {
+        #[ink(topic)]
+        by: AccountId,
+        reason: String,
+    }
+
+    #[ink(event)]
+    pub struct ev_unpaused {
+        #[ink(topic)]
+        by: AccountId,
+        reason: String,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_scheduled {
+        #[ink(topic)]
+        id: u32,
+        executable_at: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_cancelled {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct ev_change_executed {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_proposed {
+        #[ink(topic)]
+        role: RoleType,
+        from: AccountId,
+        to: AccountId,
+        deadline: u64,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_accepted {
+        #[ink(topic)]
+        role: RoleType,
+        from: AccountId,
+        to: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ev_handover_cancelled {
+        #[ink(topic)]
+        role: RoleType,
+        to: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct JobStatusChanged {
+        #[ink(topic)]
+        job_id: u32,
+        from: JobStatus,
+        to: JobStatus,
+    }
+
+    #[ink(event)]
+    pub struct ev_keeper_rewarded {
+        #[ink(topic)]
+        keeper: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ev_payment_failed {
+        #[ink(topic)]
+        job_id: u32,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+        error_code: u8,
+        payment_retries: u8,
+    }
+
     #[ink(storage)]
     #[derive(Default, SpreadAllocate, Storage)]
     pub struct TreasuryManager {
         #[storage_field]
         access: access_control::Data,
+        #[storage_field]
+        timelock: timelock::Data,
+        #[storage_field]
+        pause: pausable::Data,
         contract_administrator: AccountId,
         contract_manager: AccountId,
+        pending_admin: Option<PendingHandover>,
+        pending_manager: Option<PendingHandover>,
+        approvers: Vec<AccountId>, //always includes contract_administrator
+        approval_threshold: u8,
+        proposals: Mapping<OperationHash, Proposal>,
         next_id: u32,
         treasury_token_symbol: String,
         treasury_token_address: AccountId,
         jobs: Mapping<u32, JobInfo>,
         open_jobs_ids: Vec<u32>,
         pending_jobs_ids: Vec<u32>,
+        partially_paid_jobs_ids: Vec<u32>,
         completed_jobs_ids: Vec<u32>,
+        cancelled_jobs_ids: Vec<u32>,
+        paused_jobs_ids: Vec<u32>,
         native_payments_ids: Vec<u32>,
         native_payments_usd_ids: Vec<u32>,
         non_native_payments_ids: Vec<u32>,
+        failed_jobs_ids: Vec<u32>,
+        max_payment_retries: u8,
+        max_price_age: u64, //ms an oracle price can be old and still be paid from
+        swap_tolerance_bps: u16, //slippage accepted on swaps against the treasury's own oracle reading
+        //where the next batch of each sweep starts
+        open_jobs_cursor: u32,
+        pending_jobs_cursor: u32,
+        payment_queue_cursors: Vec<u32>,
+        //poke batch size and bounty in treasury tokens, capped per period (ms)
+        keeper_batch_size: u32,
+        keeper_bounty: Balance,
+        keeper_bounty_cap: Balance,
+        keeper_bounty_period: u64,
+        keeper_period_start: u64,
+        keeper_paid_in_period: Balance,
         non_native_tokens_vec: Vec<AccountId>,
         oracle_dex_address: AccountId,
         foreign_assets: Mapping<String, AccountId>,
         foreign_assets_vec: Vec<String>,
-        check_points_intervals: Vec<u64>,
-        liability_in_treasury: Vec<Balance>,
-        liability_in_usdt_tokens: Vec<Balance>,
-        liability_in_usdt_tokens_treasury: Vec<Balance>,
-        liability_health: Vec<u8>,
-        liabilities_thresholds: Vec<u8>,
+        payment_routes: Mapping<AccountId, Vec<AccountId>>, //token => path from the treasury token, direct if missing
+        liability_horizons: Vec<LiabilityHorizon>,
+        liability_reports: Vec<LiabilityReport>,
         fake_timestamp: u64,
     }
 
     impl TreasureManager for TreasuryManager {
+        #[ink(message)]
+        fn propose_operation(
+            &mut self,
+            operation: Operation,
+        ) -> Result<OperationHash, TreasuryManagerError> {
+            let caller = self.env().caller();
+            if !self.approvers.contains(&caller) {
+                return Err(TreasuryManagerError::NotApprover);
+            }
+            if let Operation::SetApprovers {
+                approvers,
+                threshold,
+            } = &operation
+            {
+                self.check_approvers(approvers, *threshold)?;
+            }
+
+            let now = self.env().block_timestamp();
+            let hash = self
+                .env()
+                .hash_encoded::<ink_env::hash::Blake2x256, _>(&operation);
+            if let Some(proposal) = self.proposals.get(&hash) {
+                if now <= proposal.expires_at {
+                    return Err(TreasuryManagerError::ProposalExists);
+                }
+                self.env().emit_event(ev_operation_expired { hash });
+            }
+
+            let proposal = Proposal {
+                operation,
+                proposer: caller,
+                approvals: Vec::new(),
+                expires_at: now + PROPOSAL_LIFETIME,
+            };
+            self.proposals.insert(&hash, &proposal);
+            self.env().emit_event(ev_operation_proposed {
+                hash,
+                proposer: caller,
+                operation: proposal.operation.clone(),
+                expires_at: proposal.expires_at,
+            });
+
+            self.record_approval(hash, proposal, caller)?;
+            Ok(hash)
+        }
+
+        #[ink(message)]
+        fn approve_operation(&mut self, hash: OperationHash) -> Result<(), TreasuryManagerError> {
+            let caller = self.env().caller();
+            if !self.approvers.contains(&caller) {
+                return Err(TreasuryManagerError::NotApprover);
+            }
+            let proposal = self
+                .proposals
+                .get(&hash)
+                .ok_or(TreasuryManagerError::UnknownProposal)?;
+            if self.env().block_timestamp() > proposal.expires_at {
+                self.proposals.remove(&hash);
+                self.env().emit_event(ev_operation_expired { hash });
+                return Err(TreasuryManagerError::ProposalExpired);
+            }
+            if proposal.approvals.contains(&caller) {
+                return Err(TreasuryManagerError::AlreadyApproved);
+            }
+            self.record_approval(hash, proposal, caller)
+        }
+
+        #[ink(message)]
+        fn get_proposal(&self, hash: OperationHash) -> Option<Proposal> {
+            self.proposals.get(&hash)
+        }
+
+        #[ink(message)]
+        fn get_approvers(&self) -> (Vec<AccountId>, u8) {
+            (self.approvers.clone(), self.approval_threshold)
+        }
+
+        #[ink(message)]
+        fn accept_manager(&mut self) -> Result<(), TreasuryManagerError> {
+            let caller = self.env().caller();
+            self.pending_manager
+                .ok_or(TreasuryManagerError::NoPendingHandover)?
+                .check_acceptance(caller, self.env().block_timestamp())?;
+
+            let previous_manager = self.contract_manager;
+            self._do_revoke_role(MANAGER, previous_manager);
+            self._setup_role(MANAGER, caller);
+            self.contract_manager = caller;
+            self.pending_manager = None;
+
+            self.env().emit_event(ev_handover_accepted {
+                role: MANAGER,
+                from: previous_manager,
+                to: caller,
+            });
+            Ok(())
+        }
+
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        fn set_manager(&mut self, account: AccountId) -> Result<(), AccessControlError> {
-            self.renounce_role(MANAGER, self.contract_manager);
-            self._setup_role(MANAGER, account);
-            self.contract_manager = account;
+        fn cancel_manager_handover(&mut self) -> Result<(), TreasuryManagerError> {
+            let pending = self
+                .pending_manager
+                .take()
+                .ok_or(TreasuryManagerError::NoPendingHandover)?;
+            self.env().emit_event(ev_handover_cancelled {
+                role: MANAGER,
+                to: pending.account,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn accept_admin(&mut self) -> Result<(), TreasuryManagerError> {
+            let caller = self.env().caller();
+            self.pending_admin
+                .ok_or(TreasuryManagerError::NoPendingHandover)?
+                .check_acceptance(caller, self.env().block_timestamp())?;
+
+            let previous_administrator = self.contract_administrator;
+            self._do_revoke_role(ADMIN, previous_administrator);
+            self._setup_role(ADMIN, caller);
+            if self.has_role(GUARDIAN, previous_administrator) {
+                self._do_revoke_role(GUARDIAN, previous_administrator);
+                self._setup_role(GUARDIAN, caller);
+            }
+            self.hand_over_approver_seat(previous_administrator, caller);
+            self.contract_administrator = caller;
+            self.pending_admin = None;
+
+            self.env().emit_event(ev_handover_accepted {
+                role: ADMIN,
+                from: previous_administrator,
+                to: caller,
+            });
             Ok(())
         }
 
-        ///Add Job Should be only for ADMIN
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
+        fn cancel_admin_handover(&mut self) -> Result<(), TreasuryManagerError> {
+            let pending = self
+                .pending_admin
+                .take()
+                .ok_or(TreasuryManagerError::NoPendingHandover)?;
+            self.env().emit_event(ev_handover_cancelled {
+                role: ADMIN,
+                to: pending.account,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn get_pending_manager(&self) -> Option<PendingHandover> {
+            self.pending_manager
+        }
+
+        #[ink(message)]
+        fn get_pending_admin(&self) -> Option<PendingHandover> {
+            self.pending_admin
+        }
+
+        ///Add Job Should be only for ADMIN
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_role(ADMIN))]
         fn add_job(
             &mut self,
             title: String,
@@ -178,13 +532,27 @@ pub mod treasury_manager {
             requested_value: Balance,
             payment_type: PaymentType,
             payment_schedule: Vec<u64>,
+            payment_plan: PaymentPlan,
             payee_accounts: Vec<AccountId>,
-        ) -> Result<(), AccessControlError> {
-            assert!(
-                requested_token == self.treasury_token_address
-                    || self.non_native_tokens_vec.contains(&requested_token),
-                "requested_token must be registered"
-            );
+            payee_shares: Vec<u16>,
+        ) -> Result<(), TreasuryManagerError> {
+            if requested_token != self.treasury_token_address
+                && !self.non_native_tokens_vec.contains(&requested_token)
+            {
+                return Err(TreasuryManagerError::UnregisteredToken);
+            }
+            if payee_accounts.is_empty() {
+                return Err(TreasuryManagerError::InvalidJob);
+            }
+            let instalment_amounts = Self::resolve_payment_plan(
+                &payment_type,
+                requested_value,
+                &payment_schedule,
+                &payment_plan,
+            )?;
+
+            let payee_shares = Self::resolve_payee_shares(&payee_accounts, payee_shares)?;
+            let payee_amounts_due = vec![0; payee_accounts.len()];
 
             let mut _value_in_usd = value_in_usd;
             if requested_token != self.treasury_token_address {
@@ -201,9 +569,20 @@ pub mod treasury_manager {
                 requested_value,
                 payment_type,
                 payment_schedule,
+                instalment_amounts,
+                cliff: payment_plan.cliff,
                 payee_accounts,
+                payee_shares,
+                payee_amounts_swapped: vec![(0, 0); payee_amounts_due.len()],
+                payee_amounts_due,
+                total_paid: 0,
                 next_installment_pointer: 0,
+                streamed_amount: 0,
+                status: JobStatus::Open,
                 position_in_vec: self.open_jobs_ids.len() as u32,
+                payment_retries: 0,
+                last_error_code: 0,
+                cancellation_reason: String::new(),
             };
 
             self.jobs.insert(&self.next_id, &job);
@@ -214,42 +593,180 @@ pub mod treasury_manager {
         }
 
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
-        fn remove_job_info(&mut self, id: u32) -> Result<(), AccessControlError> {
-            self.jobs.remove(&id);
+        #[modifiers(when_not_paused, only_role(ADMIN))]
+        fn cancel_job(&mut self, id: u32, reason: String) -> Result<(), TreasuryManagerError> {
+            self.transition_job(id, JobStatus::Cancelled)?;
+
+            //CLAWBACK: what is still scheduled stays in the treasury.
+            //Dues already accrued, a stream's unclaimed accrual included, are owed to the payees and stay queued until paid
+            let mut current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            Self::accrue_stream(&mut current_job, self.env().block_timestamp());
+            let clawed_back = Self::liability_until(&current_job, u64::MAX);
+            current_job.cancellation_reason = reason.clone();
+            self.jobs.insert(&id, &current_job);
+
+            if current_job.payee_amounts_due.iter().any(|due| *due > 0) {
+                self.push_to_payment_queue(&current_job);
+            } else {
+                self.remove_from_payment_queues(id);
+            }
+
+            self.env().emit_event(ev_job_cancelled {
+                job_id: id,
+                reason,
+                clawed_back,
+            });
+
             Ok(())
         }
 
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
-        fn admin_withdrawal(&mut self, amount: Balance) -> Result<(), AccessControlError> {
-            //APPROVE FIRST
-            PSP22Ref::approve(&self.treasury_token_address, self.env().caller(), amount)
-                .expect("Approval for ADMIN withdrawing treasury_token did not go well");
-
-            // PSP22Ref::transfer(
-            //     &self.treasury_token_address,
-            //     self.env().caller(),
-            //     amount,
-            //     Vec::<u8>::new(),
-            // )
-            // .expect("Transfer to ADMIN did not go well");
+        #[modifiers(when_not_paused, only_role(ADMIN))]
+        fn amend_job(
+            &mut self,
+            id: u32,
+            requested_value: Balance,
+            payment_schedule: Vec<u64>,
+            payment_plan: PaymentPlan,
+            payee_accounts: Vec<AccountId>,
+            payee_shares: Vec<u16>,
+        ) -> Result<(), TreasuryManagerError> {
+            let mut current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            if !matches!(
+                current_job.status,
+                JobStatus::Open | JobStatus::Pending | JobStatus::PartiallyPaid | JobStatus::Paused
+            ) {
+                return Err(TreasuryManagerError::InvalidJobState);
+            }
+            if payee_accounts.is_empty() {
+                return Err(TreasuryManagerError::InvalidJob);
+            }
 
-            //SHOULD EMMIT EVENT
+            //a stream that has ended has nothing left to amend
+            let now = self.env().block_timestamp();
+            if let PaymentType::Streaming { end, .. } = current_job.payment_type {
+                if now >= end {
+                    return Err(TreasuryManagerError::InvalidJobState);
+                }
+            }
+
+            let instalment_amounts = Self::resolve_payment_plan(
+                &current_job.payment_type,
+                requested_value,
+                &payment_schedule,
+                &payment_plan,
+            )?;
+            let payee_shares = Self::resolve_payee_shares(&payee_accounts, payee_shares)?;
+
+            //a stream has already released what accrued up to now. It becomes due to the current payees
+            //and the amended stream runs from now to the same end, so the elapsed time is not streamed again
+            let mut payment_type = current_job.payment_type.clone();
+            if let PaymentType::Streaming { start, end } = current_job.payment_type {
+                Self::accrue_stream(&mut current_job, now);
+                payment_type = PaymentType::Streaming {
+                    start: start.max(now),
+                    end,
+                };
+            }
+
+            //dues already owed to the current payees cannot be moved to different ones
+            let has_dues = current_job.payee_amounts_due.iter().any(|due| *due > 0);
+            if has_dues && payee_accounts != current_job.payee_accounts {
+                return Err(TreasuryManagerError::InvalidJobState);
+            }
+            let (payee_amounts_due, payee_amounts_swapped) = if has_dues {
+                (
+                    current_job.payee_amounts_due.clone(),
+                    current_job.payee_amounts_swapped.clone(),
+                )
+            } else {
+                (
+                    vec![0; payee_accounts.len()],
+                    vec![(0, 0); payee_accounts.len()],
+                )
+            };
+
+            let updated_job = JobInfo {
+                requested_value,
+                payment_type,
+                payment_schedule,
+                instalment_amounts,
+                cliff: payment_plan.cliff,
+                payee_accounts,
+                payee_shares,
+                payee_amounts_due,
+                payee_amounts_swapped,
+                next_installment_pointer: 0,
+                streamed_amount: 0,
+                ..current_job
+            };
+            let status = updated_job.status;
+            self.jobs.insert(&id, &updated_job);
+
+            //the new schedule starts over so a job being paid waits again in open jobs
+            if status == JobStatus::Pending || status == JobStatus::PartiallyPaid {
+                self.transition_job(id, JobStatus::Open)?;
+            }
+            if has_dues {
+                let current_job: JobInfo =
+                    self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+                self.queue_payment(&current_job);
+            }
+
+            self.env().emit_event(ev_job_amended {
+                job_id: id,
+                requested_value,
+            });
 
             Ok(())
         }
 
+        ///Paused jobs are skipped by the job checks and payments until resumed
         #[ink(message)]
-        #[modifiers(only_role(ADMIN))]
-        fn terminate_me(&mut self) -> Result<(), AccessControlError> {
-            self.env().terminate_contract(self.env().caller());
+        #[modifiers(when_not_paused, only_role(ADMIN))]
+        fn pause_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            self.transition_job(id, JobStatus::Paused)?;
+            self.remove_from_payment_queues(id);
+
+            Ok(())
+        }
+
+        ///A resumed job goes back to open jobs and check_open_jobs moves it on once it is due
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_role(ADMIN))]
+        fn resume_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            if current_job.status != JobStatus::Paused {
+                return Err(TreasuryManagerError::InvalidJobState);
+            }
+
+            self.transition_job(id, JobStatus::Open)?;
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            if current_job.payee_amounts_due.iter().any(|due| *due > 0) {
+                self.queue_payment(&current_job);
+            }
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_role(ADMIN))]
+        fn remove_job_info(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            self.detach_from_vec(current_job.status, id)?;
+            self.remove_from_payment_queues(id);
+            self.jobs.remove(&id);
             Ok(())
         }
 
         #[ink(message)]
         #[modifiers(only_role(ADMIN))]
-        fn make_deposit(&mut self, amount: Balance) -> Result<(), AccessControlError> {
+        fn make_deposit(&mut self, amount: Balance) -> Result<(), TreasuryManagerError> {
             let from_caller = self.env().caller().clone();
             let contract = self.env().account_id().clone();
 
@@ -262,86 +779,234 @@ pub mod treasury_manager {
             )
             .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
             .fire()
-            .unwrap();
+            .map_err(|_| TreasuryManagerError::TransferFailed)??;
 
             //SHOULD EMMIT EVENT
 
             Ok(())
         }
 
+        ///GUARDIAN halts payments, swaps and job transitions. Queries keep working
+        #[ink(message)]
+        #[modifiers(only_role(GUARDIAN))]
+        fn pause(&mut self, reason: String) -> Result<(), TreasuryManagerError> {
+            self._pause::<TreasuryManagerError>()?;
+            self.env().emit_event(ev_paused {
+                by: self.env().caller(),
+                reason,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_role(ADMIN))]
+        fn unpause(&mut self, reason: String) -> Result<(), TreasuryManagerError> {
+            self._unpause::<TreasuryManagerError>()?;
+            self.env().emit_event(ev_unpaused {
+                by: self.env().caller(),
+                reason,
+            });
+            Ok(())
+        }
+
         //
     }
 
     #[ink(impl)]
     impl TreasuryManager {
-        pub fn swap_in_vecs(&mut self, what_to_swap: MoveJobs, id: u32) {
-            let mut origin_vec_ids = &mut Vec::new();
-            let mut dest_vec_ids = &mut Vec::new();
-
-            match what_to_swap {
-                MoveJobs::OpenToPending => {
-                    origin_vec_ids = &mut self.open_jobs_ids;
-                    dest_vec_ids = &mut self.pending_jobs_ids;
-                }
-                MoveJobs::PendingToCompleted => {
-                    origin_vec_ids = &mut self.pending_jobs_ids;
-                    dest_vec_ids = &mut self.completed_jobs_ids;
-                }
+        ///The only place a job changes status. Rejects edges JobStatus does not allow,
+        ///moves the id between the status vectors and emits JobStatusChanged
+        fn transition_job(&mut self, id: u32, to: JobStatus) -> Result<(), TreasuryManagerError> {
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            let from = current_job.status;
+            if !from.can_transition_to(&to) {
+                return Err(TreasuryManagerError::InvalidJobState);
             }
 
-            ink_env::debug_println!("1 origin_vec_ids: {:?}", origin_vec_ids);
-            ink_env::debug_println!("1 dest_vec_ids: {:?}", dest_vec_ids);
+            self.detach_from_vec(from, id)?;
+            self.attach_to_vec(to, id)?;
 
-            //get current job with id.
-            let current_job: JobInfo = self.jobs.get(&id).unwrap();
-            // positing in origin_vec_ids that this job id exists
-            let position_in_current_vec = current_job.position_in_vec;
-            ink_env::debug_println!("position_in_current_vec: {:?}", position_in_current_vec);
+            self.env().emit_event(JobStatusChanged {
+                job_id: id,
+                from,
+                to,
+            });
+
+            Ok(())
+        }
+
+        fn jobs_vec(&self, status: JobStatus) -> &Vec<u32> {
+            match status {
+                JobStatus::Open => &self.open_jobs_ids,
+                JobStatus::Pending => &self.pending_jobs_ids,
+                JobStatus::PartiallyPaid => &self.partially_paid_jobs_ids,
+                JobStatus::Completed => &self.completed_jobs_ids,
+                JobStatus::Cancelled => &self.cancelled_jobs_ids,
+                JobStatus::Failed => &self.failed_jobs_ids,
+                JobStatus::Paused => &self.paused_jobs_ids,
+            }
+        }
 
-            let origin_vec_size = origin_vec_ids.len() as u32;
+        fn jobs_vec_mut(&mut self, status: JobStatus) -> &mut Vec<u32> {
+            match status {
+                JobStatus::Open => &mut self.open_jobs_ids,
+                JobStatus::Pending => &mut self.pending_jobs_ids,
+                JobStatus::PartiallyPaid => &mut self.partially_paid_jobs_ids,
+                JobStatus::Completed => &mut self.completed_jobs_ids,
+                JobStatus::Cancelled => &mut self.cancelled_jobs_ids,
+                JobStatus::Failed => &mut self.failed_jobs_ids,
+                JobStatus::Paused => &mut self.paused_jobs_ids,
+            }
+        }
 
-            if position_in_current_vec < origin_vec_size {
-                //find last elemment in the origin_vec_ids array
-                let last_element_job_id = origin_vec_ids[(origin_vec_size - 1) as usize];
+        ///Take id out of the vector. The last id takes its place and gets its position_in_vec updated.
+        ///If a sweep has already passed id, the last id it has not checked yet takes the place of the last one
+        ///it checked instead and the sweep's cursor moves back one, so nothing is skipped until it wraps
+        fn detach_from_vec(
+            &mut self,
+            origin: JobStatus,
+            id: u32,
+        ) -> Result<(), TreasuryManagerError> {
+            //get current job with id.
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            // positing in origin_vec_ids that this job id exists
+            let position_in_current_vec = current_job.position_in_vec as usize;
+            let checked = self.checked_by_sweep(origin);
 
-                //get the last job
-                let mut last_job: JobInfo = self.jobs.get(&last_element_job_id).unwrap().clone();
+            let origin_vec_ids = self.jobs_vec_mut(origin);
+            if origin_vec_ids.get(position_in_current_vec) != Some(&id) {
+                return Err(TreasuryManagerError::InvalidJobState);
+            }
+            let mut moved_positions = vec![position_in_current_vec];
+            match checked {
+                Some(checked) if position_in_current_vec < checked => {
+                    origin_vec_ids.swap(position_in_current_vec, checked - 1);
+                    origin_vec_ids.swap_remove(checked - 1);
+                    if checked - 1 != position_in_current_vec {
+                        moved_positions.push(checked - 1);
+                    }
+                    match origin {
+                        JobStatus::Open => self.open_jobs_cursor -= 1,
+                        _ => self.pending_jobs_cursor -= 1,
+                    }
+                }
+                _ => {
+                    origin_vec_ids.swap_remove(position_in_current_vec);
+                }
+            }
 
-                last_job = JobInfo {
-                    position_in_vec: position_in_current_vec,
-                    ..last_job
+            // the ids that moved now sit in these positions
+            for position in moved_positions {
+                let moved_job_id = match self.jobs_vec_mut(origin).get(position) {
+                    Some(job_id) => *job_id,
+                    None => continue,
+                };
+                let moved_job: JobInfo = self
+                    .jobs
+                    .get(&moved_job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+                let moved_job = JobInfo {
+                    position_in_vec: position as u32,
+                    ..moved_job
                 };
-                ink_env::debug_println!("last_job: {:?}", last_job);
+                self.jobs.insert(&moved_job.id, &moved_job);
+            }
 
-                self.jobs.insert(&last_job.id, &last_job);
+            Ok(())
+        }
 
-                // place the last job id in this positoon. last job has been updated and its ide can now be deleted
-                origin_vec_ids[position_in_current_vec as usize] = last_element_job_id;
+        ///How many ids at the front of the status' vector the open or pending sweep has checked this round.
+        ///The pending sweep runs over pending_jobs_ids followed by partially_paid_jobs_ids
+        fn checked_by_sweep(&self, status: JobStatus) -> Option<usize> {
+            let pending_len = self.pending_jobs_ids.len();
+            match status {
+                JobStatus::Open => {
+                    Some((self.open_jobs_cursor as usize).min(self.open_jobs_ids.len()))
+                }
+                JobStatus::Pending => Some((self.pending_jobs_cursor as usize).min(pending_len)),
+                JobStatus::PartiallyPaid => Some(
+                    (self.pending_jobs_cursor as usize)
+                        .saturating_sub(pending_len)
+                        .min(self.partially_paid_jobs_ids.len()),
+                ),
+                _ => None,
             }
-            //last job id is deleted
-            origin_vec_ids.pop();
+        }
 
+        fn attach_to_vec(&mut self, dest: JobStatus, id: u32) -> Result<(), TreasuryManagerError> {
+            let current_job: JobInfo =
+                self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            let dest_vec_ids = self.jobs_vec_mut(dest);
             let updated_job = JobInfo {
+                status: dest,
                 position_in_vec: dest_vec_ids.len() as u32,
                 ..current_job
             };
-            ink_env::debug_println!("updated_job: {:?}", updated_job);
+            dest_vec_ids.push(id);
             self.jobs.insert(&updated_job.id, &updated_job);
 
-            dest_vec_ids.push(updated_job.id);
+            Ok(())
+        }
+
+        fn remove_from_payment_queues(&mut self, id: u32) {
+            for which in [
+                PaymentQueue::Native,
+                PaymentQueue::NativeUsd,
+                PaymentQueue::NonNative,
+            ] {
+                self.remove_from_payment_queue(which, id);
+            }
+        }
 
-            ink_env::debug_println!("2 origin_vec_ids: {:?}", origin_vec_ids);
-            ink_env::debug_println!("2 dest_vec_ids: {:?}", dest_vec_ids);
+        ///Take id out of the queue. The queue's cursor moves back with the entries after it,
+        ///so the next batch neither skips nor repeats one
+        fn remove_from_payment_queue(&mut self, which: PaymentQueue, id: u32) {
+            let queue = self.payment_queue_mut(which);
+            if let Some(position) = queue.iter().position(|job_id| *job_id == id) {
+                queue.remove(position);
+                let cursor = &mut self.payment_queue_cursors[which as usize];
+                if (position as u32) < *cursor {
+                    *cursor -= 1;
+                }
+            }
         }
     }
 
+    const MAX_LIABILITY_HORIZONS: usize = 8;
+
+    ///Upper bound on jobs get_jobs reads in one call whatever the filter
+    const MAX_JOBS_SCANNED: u32 = 200;
+
     const ADMIN: RoleType = ink_lang::selector_id!("ADMIN");
     const MANAGER: RoleType = ink_lang::selector_id!("MANAGER");
+    const KEEPER: RoleType = ink_lang::selector_id!("KEEPER");
+    const TREASURER: RoleType = ink_lang::selector_id!("TREASURER");
 
     impl AccessControl for TreasuryManager {}
 
-    impl TreasuryManager {
-        #[ink(constructor)]
+    impl Timelock for TreasuryManager {}
+
+    impl Pausable for TreasuryManager {}
+
+    impl timelock::Internal for TreasuryManager {
+        fn _emit_change_scheduled(&self, id: u32, executable_at: u64) {
+            self.env()
+                .emit_event(ev_change_scheduled { id, executable_at });
+        }
+
+        fn _emit_change_cancelled(&self, id: u32) {
+            self.env().emit_event(ev_change_cancelled { id });
+        }
+
+        fn _emit_change_executed(&self, id: u32) {
+            self.env().emit_event(ev_change_executed { id });
+        }
+    }
+
+    impl TreasuryManager {
+        #[ink(constructor)]
         pub fn new(
             contract_administrator: AccountId,
             contract_manager: AccountId,
@@ -360,34 +1025,62 @@ pub mod treasury_manager {
                 instance
                     .grant_role(MANAGER, contract_manager)
                     .expect("Should grant the MANAGER role");
+                instance
+                    .grant_role(GUARDIAN, contract_administrator)
+                    .expect("Should grant the GUARDIAN role");
                 instance.contract_administrator = contract_administrator;
                 instance.contract_manager = contract_manager;
+                instance.pending_admin = None;
+                instance.pending_manager = None;
+                instance.approvers = vec![contract_administrator];
+                instance.approval_threshold = 1;
+                instance.proposals = Default::default();
+                instance.timelock.min_delay = DEFAULT_TIMELOCK_DELAY;
                 instance.next_id = 0;
                 instance.treasury_token_symbol = treasury_token_symbol;
                 instance.treasury_token_address = treasury_token_address;
                 instance.jobs = Default::default();
                 instance.open_jobs_ids = Vec::new();
                 instance.pending_jobs_ids = Vec::new();
+                instance.partially_paid_jobs_ids = Vec::new();
                 instance.completed_jobs_ids = Vec::new();
+                instance.cancelled_jobs_ids = Vec::new();
+                instance.paused_jobs_ids = Vec::new();
                 instance.native_payments_ids = Default::default();
                 instance.non_native_payments_ids = Default::default();
                 instance.native_payments_usd_ids = Default::default();
+                instance.failed_jobs_ids = Default::default();
+                instance.max_payment_retries = 3;
+                instance.max_price_age = 3_600_000;
+                instance.swap_tolerance_bps = 100;
+                instance.open_jobs_cursor = 0;
+                instance.pending_jobs_cursor = 0;
+                instance.payment_queue_cursors = vec![0, 0, 0];
+                instance.keeper_batch_size = 20;
+                instance.keeper_bounty = 0;
+                instance.keeper_bounty_cap = 0;
+                instance.keeper_bounty_period = 86_400_000;
+                instance.keeper_period_start = 0;
+                instance.keeper_paid_in_period = 0;
                 instance.non_native_tokens_vec = vec![usdt_token_address]; //Default::default();
                 instance.oracle_dex_address = oracle_dex_address;
                 instance
                     .foreign_assets
                     .insert(&String::from("USDT"), &usdt_token_address);
                 instance.foreign_assets_vec = vec![String::from("USDT")];
-                instance.liabilities_thresholds = vec![
-                    (100 - liabilities_threshold_level),
-                    (100 - liabilities_threshold_level * 2),
-                    (100 + liabilities_threshold_level * 3),
-                ];
-                instance.check_points_intervals = vec![100, 200, 300];
-                instance.liability_in_treasury = vec![0, 0, 0, 0];
-                instance.liability_in_usdt_tokens = vec![0, 0, 0, 0];
-                instance.liability_in_usdt_tokens_treasury = vec![0, 0, 0, 0];
-                instance.liability_health = vec![2, 2, 2, 2];
+                instance.payment_routes = Default::default();
+                instance.liability_horizons =
+                    [("ALL", u64::MAX), ("2D", 100), ("7D", 200), ("30D", 300)]
+                        .iter()
+                        .map(|(name, duration)| LiabilityHorizon {
+                            name: String::from(*name),
+                            duration: *duration,
+                            warning: 100 - liabilities_threshold_level * 2,
+                            critical: 100 - liabilities_threshold_level,
+                            top_up_target: 100 + liabilities_threshold_level as u16 * 3,
+                        })
+                        .collect();
+                instance.liability_reports = Vec::new();
 
                 instance.fake_timestamp = Default::default();
             })
@@ -409,109 +1102,627 @@ pub mod treasury_manager {
             self.env().block_timestamp()
         }
 
+        ///Move up to max_items open jobs whose first payment is due to Pending.
+        ///Carries on from where the previous call stopped and starts over once the end of open jobs is reached
         #[ink(message)]
-        pub fn check_open_jobs(&mut self) {
-            let mut queued_to_move_job_ids = Vec::new();
+        #[modifiers(when_not_paused, only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn check_open_jobs(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            self._check_open_jobs(max_items)
+        }
 
-            for job_id in &self.open_jobs_ids {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
+        fn _check_open_jobs(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            let mut processed: u32 = 0;
+            let mut completed: u32 = 0;
+            let mut cursor = self.open_jobs_cursor as usize;
+
+            while processed < max_items && cursor < self.open_jobs_ids.len() {
+                let job_id = self.open_jobs_ids[cursor];
+                let current_job: JobInfo = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+                let first_due_timestamp = match current_job.payment_type {
+                    PaymentType::Streaming { start, .. } => start,
+                    _ => {
+                        if current_job.payment_schedule.is_empty() {
+                            return Err(TreasuryManagerError::EmptySchedule);
+                        }
+                        Self::instalment_due_timestamp(&current_job, 0)
+                    }
+                };
+                processed += 1;
 
                 // if self.fake_timestamp > current_job.payment_schedule[0]
-                if self.env().block_timestamp() > current_job.payment_schedule[0] {
-                    queued_to_move_job_ids.push(current_job.id);
+                if self.env().block_timestamp() > first_due_timestamp {
+                    //the last open job takes this position and is checked next
+                    self.transition_job(job_id, JobStatus::Pending)?;
+                    completed += 1;
+                } else {
+                    cursor += 1;
                 }
             }
 
-            // ink_env::debug_println!("queued_to_move_job_ids: {:?}", queued_to_move_job_ids);
+            let progress =
+                Self::batch_progress(processed, completed, cursor, self.open_jobs_ids.len());
+            self.open_jobs_cursor = if progress.remaining == 0 {
+                0
+            } else {
+                cursor as u32
+            };
 
-            for job_id in queued_to_move_job_ids {
-                self.move_job_from_open_to_pending(job_id);
-            }
+            Ok(progress)
         }
 
+        ///Accrue what became due on up to max_items Pending and PartiallyPaid jobs and queue their payments.
+        ///Carries on from where the previous call stopped and starts over once the end is reached
         #[ink(message)]
-        pub fn check_pending_jobs(&mut self) {
-            let mut queued_to_move_job_ids = Vec::new();
+        #[modifiers(when_not_paused, only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn check_pending_jobs(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            self._check_pending_jobs(max_items)
+        }
 
-            for job_id in &self.pending_jobs_ids {
-                let mut current_job: JobInfo = self.jobs.get(&job_id).unwrap();
+        fn _check_pending_jobs(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            let mut processed: u32 = 0;
+            let mut completed: u32 = 0;
+            let mut cursor = self.pending_jobs_cursor as usize;
+
+            while processed < max_items {
+                let job_id = match self.due_job_at(cursor) {
+                    Some(job_id) => job_id,
+                    None => break,
+                };
+                processed += 1;
+                let before: JobInfo = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+
+                //nothing left to accrue. Jobs complete here only if there was nothing left to pay either
+                if self.accrue_due_payments(job_id)? {
+                    self.refresh_payment_status(job_id)?;
+                }
 
-                match current_job.payment_type {
-                    PaymentType::OneOffFutureTime => {
-                        //categorise and push payment
-                        if current_job.requested_token == self.treasury_token_address {
-                            if current_job.value_in_usd {
-                                self.native_payments_usd_ids.push(current_job.id);
-                            } else {
-                                self.native_payments_ids.push(current_job.id);
-                            }
-                        } else {
-                            self.non_native_payments_ids.push(current_job.id);
-                        }
+                //only an accrual or a status change counts as work
+                let after: JobInfo = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+                if after.status != before.status
+                    || after.payee_amounts_due != before.payee_amounts_due
+                {
+                    completed += 1;
+                }
 
-                        // ink_env::debug_println!(
-                        //     "PAYMENT OF PaymentType::OneOffFutureTime id: {:?}",
-                        //     current_job.id
-                        // );
+                //a job that changed status was swapped out and the one now at cursor is checked next
+                if self.due_job_at(cursor) == Some(job_id) {
+                    cursor += 1;
+                }
+            }
+
+            let due_jobs_len = self.pending_jobs_ids.len() + self.partially_paid_jobs_ids.len();
+            let progress = Self::batch_progress(processed, completed, cursor, due_jobs_len);
+            self.pending_jobs_cursor = if progress.remaining == 0 {
+                0
+            } else {
+                cursor as u32
+            };
+
+            Ok(progress)
+        }
+
+        ///Job at position in pending_jobs_ids followed by partially_paid_jobs_ids
+        fn due_job_at(&self, position: usize) -> Option<u32> {
+            match self.pending_jobs_ids.get(position) {
+                Some(job_id) => Some(*job_id),
+                None => self
+                    .partially_paid_jobs_ids
+                    .get(position - self.pending_jobs_ids.len())
+                    .cloned(),
+            }
+        }
+
+        fn batch_progress(
+            processed: u32,
+            completed: u32,
+            cursor: usize,
+            len: usize,
+        ) -> BatchProgress {
+            BatchProgress {
+                processed,
+                completed,
+                remaining: len.saturating_sub(cursor) as u32,
+            }
+        }
+
+        ///Accrue and queue whatever became due on the job. Returns true once there is nothing left to accrue
+        fn accrue_due_payments(&mut self, job_id: u32) -> Result<bool, TreasuryManagerError> {
+            let current_job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+
+            match current_job.payment_type {
+                PaymentType::OneOffFutureTime => {
+                    //already accrued, the job stays here until it is paid
+                    if current_job.next_installment_pointer > 0 {
+                        return Ok(false);
+                    }
+                    //categorise and push payment
+                    let requested_value = current_job.instalment_amounts[0];
+                    let current_job = JobInfo {
+                        next_installment_pointer: 1,
+                        ..current_job
+                    };
+                    let current_job = self.accrue_payment(current_job, requested_value);
+                    self.queue_payment(&current_job);
+
+                    // ink_env::debug_println!(
+                    //     "PAYMENT OF PaymentType::OneOffFutureTime id: {:?}",
+                    //     current_job.id
+                    // );
+
+                    Ok(true)
+                }
+                PaymentType::Instalments => {
+                    let num_of_payments = current_job.payment_schedule.len() as u32;
+                    let mut installment_num = current_job.next_installment_pointer;
+                    let mut due_value: Balance = 0;
+
+                    //a cliff can make several instalments due at once
+                    // if self.fake_timestamp
+                    while installment_num < num_of_payments
+                        && self.env().block_timestamp()
+                            > Self::instalment_due_timestamp(&current_job, installment_num)
+                    {
+                        due_value += current_job.instalment_amounts[installment_num as usize];
+                        installment_num += 1;
+                    }
 
-                        queued_to_move_job_ids.push(current_job.id);
+                    if installment_num == current_job.next_installment_pointer {
+                        return Ok(false);
                     }
-                    PaymentType::Instalments => {
-                        let installment_num = current_job.next_installment_pointer;
 
-                        // if self.fake_timestamp
-                        if self.env().block_timestamp()
-                            > current_job.payment_schedule[installment_num as usize]
-                        {
-                            //categorise and push payment
-                            if current_job.requested_token == self.treasury_token_address {
-                                if current_job.value_in_usd {
-                                    self.native_payments_usd_ids.push(current_job.id);
-                                } else {
-                                    self.native_payments_ids.push(current_job.id);
-                                }
-                            } else {
-                                self.non_native_payments_ids.push(current_job.id);
-                            }
+                    // ink_env::debug_println!(
+                    //     "PAYMENT OF PaymentType::FixedTimeIntervalInstallment id: {:?}",
+                    //     current_job.id
+                    // );
+
+                    let updated_job = JobInfo {
+                        next_installment_pointer: installment_num,
+                        ..current_job
+                    };
 
-                            // ink_env::debug_println!(
-                            //     "PAYMENT OF PaymentType::FixedTimeIntervalInstallment id: {:?}",
-                            //     current_job.id
-                            // );
+                    //categorise and push payment
+                    let updated_job = self.accrue_payment(updated_job, due_value);
+                    self.queue_payment(&updated_job);
 
-                            let num_of_payments = current_job.payment_schedule.len() as u32;
+                    Ok(installment_num == num_of_payments)
+                }
+                //Streams are paid out when a payee calls claim_stream
+                PaymentType::Streaming { .. } => Ok(false),
+            }
+        }
 
-                            if num_of_payments > 1 && installment_num < (num_of_payments - 1) {
-                                let updated_job = JobInfo {
-                                    next_installment_pointer: current_job.next_installment_pointer
-                                        + 1,
-                                    ..current_job
-                                };
+        ///Validate payment_schedule against payment_plan and return the amount due at every timestamp
+        fn resolve_payment_plan(
+            payment_type: &PaymentType,
+            requested_value: Balance,
+            payment_schedule: &Vec<u64>,
+            payment_plan: &PaymentPlan,
+        ) -> Result<Vec<Balance>, TreasuryManagerError> {
+            if let PaymentType::Streaming { start, end } = payment_type {
+                if end <= start {
+                    return Err(TreasuryManagerError::InvalidStreamWindow);
+                }
+                //the stream window replaces payment_schedule and payment_plan
+                if !payment_schedule.is_empty() || *payment_plan != PaymentPlan::default() {
+                    return Err(TreasuryManagerError::InvalidJob);
+                }
+                return Ok(Vec::new());
+            }
 
-                                self.jobs.insert(&updated_job.id, &updated_job);
-                            } else {
-                                queued_to_move_job_ids.push(current_job.id);
-                            }
-                        }
+            let num_of_payments = payment_schedule.len();
+            if num_of_payments == 0 {
+                return Err(TreasuryManagerError::EmptySchedule);
+            }
+            if *payment_type == PaymentType::OneOffFutureTime && num_of_payments != 1 {
+                return Err(TreasuryManagerError::InvalidJob);
+            }
+            if payment_schedule.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err(TreasuryManagerError::ScheduleNotIncreasing);
+            }
+            if let Some(cliff) = payment_plan.cliff {
+                if cliff > payment_schedule[num_of_payments - 1] {
+                    return Err(TreasuryManagerError::InvalidCliff);
+                }
+            }
+
+            if payment_plan.instalment_amounts.is_empty() {
+                let instalment = requested_value / num_of_payments as u128;
+                let mut instalment_amounts = vec![instalment; num_of_payments];
+                instalment_amounts[num_of_payments - 1] +=
+                    requested_value - instalment * num_of_payments as u128;
+                return Ok(instalment_amounts);
+            }
+
+            let total = payment_plan
+                .instalment_amounts
+                .iter()
+                .try_fold(0 as Balance, |sum, amount| sum.checked_add(*amount));
+            if payment_plan.instalment_amounts.len() != num_of_payments
+                || total != Some(requested_value)
+            {
+                return Err(TreasuryManagerError::InstalmentAmountsMismatch);
+            }
+            Ok(payment_plan.instalment_amounts.clone())
+        }
+
+        ///Check payee_shares against payee_accounts. A single payee may skip the share table and receive everything
+        fn resolve_payee_shares(
+            payee_accounts: &Vec<AccountId>,
+            payee_shares: Vec<u16>,
+        ) -> Result<Vec<u16>, TreasuryManagerError> {
+            let payee_shares = if payee_shares.is_empty() && payee_accounts.len() == 1 {
+                vec![PAYEE_SHARES_TOTAL]
+            } else {
+                payee_shares
+            };
+            if payee_shares.len() != payee_accounts.len()
+                || payee_shares.contains(&0)
+                || payee_shares.iter().map(|share| *share as u32).sum::<u32>()
+                    != PAYEE_SHARES_TOTAL as u32
+            {
+                return Err(TreasuryManagerError::InvalidPayeeShares);
+            }
+            Ok(payee_shares)
+        }
+
+        ///Timestamp after which instalment becomes payable, pushed back to the cliff if there is one
+        fn instalment_due_timestamp(job: &JobInfo, instalment: u32) -> u64 {
+            let timestamp = job.payment_schedule[instalment as usize];
+            match job.cliff {
+                Some(cliff) if cliff > timestamp => cliff,
+                _ => timestamp,
+            }
+        }
+
+        ///Part of requested_value a Streaming job has released by timestamp
+        fn streamed_until(job: &JobInfo, timestamp: u64) -> Balance {
+            match job.payment_type {
+                PaymentType::Streaming { start, end } => {
+                    if timestamp <= start {
+                        0
+                    } else if timestamp >= end {
+                        job.requested_value
+                    } else {
+                        //elapsed / window is below 1, so the result never exceeds requested_value
+                        mul_div(
+                            job.requested_value,
+                            (timestamp - start) as u128,
+                            (end - start) as u128,
+                            Rounding::Down,
+                        )
+                        .unwrap_or(job.requested_value)
+                        .min(job.requested_value)
                     }
                 }
+                _ => 0,
+            }
+        }
+
+        ///Amount still to become due on the job up to timestamp, in the job's denomination.
+        ///For streams it is what accrues by timestamp and has not been claimed yet. payee_amounts_due is not included
+        fn liability_until(job: &JobInfo, timestamp: u64) -> Balance {
+            match job.payment_type {
+                PaymentType::Streaming { .. } => {
+                    Self::streamed_until(job, timestamp).saturating_sub(job.streamed_amount)
+                }
+                _ => (job.next_installment_pointer..(job.payment_schedule.len() as u32))
+                    .filter(|ts_pointer| {
+                        Self::instalment_due_timestamp(job, *ts_pointer) <= timestamp
+                    })
+                    .map(|ts_pointer| job.instalment_amounts[ts_pointer as usize])
+                    .sum(),
             }
+        }
 
-            // ink_env::debug_println!("queued_to_move_job_ids: {:?}", queued_to_move_job_ids);
+        ///Split amount by basis points. The last payee takes the rounding remainder so legs add up to amount
+        fn split_by_shares(amount: Balance, payee_shares: &Vec<u16>) -> Vec<Balance> {
+            let mut legs = Vec::new();
+            let mut allocated: Balance = 0;
+            for (index, share) in payee_shares.iter().enumerate() {
+                let leg = if index + 1 == payee_shares.len() {
+                    amount - allocated
+                } else {
+                    amount * (*share as u128) / (PAYEE_SHARES_TOTAL as u128)
+                };
+                allocated += leg;
+                legs.push(leg);
+            }
+            legs
+        }
 
-            for job_id in queued_to_move_job_ids {
-                self.move_job_from_pending_to_completed(job_id);
+        ///Make what a stream released up to timestamp and was not claimed yet due to its payees
+        fn accrue_stream(job: &mut JobInfo, timestamp: u64) {
+            if let PaymentType::Streaming { .. } = job.payment_type {
+                let streamed = Self::streamed_until(job, timestamp);
+                let unclaimed = streamed.saturating_sub(job.streamed_amount);
+                let legs = Self::split_by_shares(unclaimed, &job.payee_shares);
+                for (index, leg) in legs.iter().enumerate() {
+                    job.payee_amounts_due[index] += leg;
+                }
+                job.streamed_amount = job.streamed_amount.max(streamed);
             }
         }
 
-        // #[ink(message)]
-        fn move_job_from_open_to_pending(&mut self, id: u32) {
-            self.swap_in_vecs(MoveJobs::OpenToPending, id);
+        ///Add amount, split by payee_shares, to what each payee is owed and store the job
+        fn accrue_payment(&mut self, mut job: JobInfo, amount: Balance) -> JobInfo {
+            let legs = Self::split_by_shares(amount, &job.payee_shares);
+            for (index, leg) in legs.iter().enumerate() {
+                job.payee_amounts_due[index] += leg;
+            }
+            self.jobs.insert(&job.id, &job);
+            job
         }
 
-        // #[ink(message)]
-        fn move_job_from_pending_to_completed(&mut self, id: u32) {
-            self.swap_in_vecs(MoveJobs::PendingToCompleted, id);
+        ///Push job id in the payment queue that matches its token and denomination.
+        ///A job that is already queued, Failed, Paused or Cancelled is left where it is
+        fn queue_payment(&mut self, job: &JobInfo) {
+            if matches!(
+                job.status,
+                JobStatus::Failed | JobStatus::Paused | JobStatus::Cancelled
+            ) {
+                return;
+            }
+            self.push_to_payment_queue(job);
+        }
+
+        fn push_to_payment_queue(&mut self, job: &JobInfo) {
+            let queue = if job.requested_token == self.treasury_token_address {
+                if job.value_in_usd {
+                    &mut self.native_payments_usd_ids
+                } else {
+                    &mut self.native_payments_ids
+                }
+            } else {
+                &mut self.non_native_payments_ids
+            };
+            if !queue.contains(&job.id) {
+                queue.push(job.id);
+            }
+        }
+
+        ///Keep the failure on the job and emit ev_payment_failed.
+        ///Returns true if the job should stay in its payment queue, false once it has been parked as Failed.
+        ///An Open job (amended with dues left) keeps retrying until it is Pending again
+        fn register_payment_failure(
+            &mut self,
+            job_id: u32,
+            to: AccountId,
+            amount: Balance,
+            error: TreasuryManagerError,
+        ) -> Result<bool, TreasuryManagerError> {
+            let mut job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+            job.payment_retries = job.payment_retries.saturating_add(1);
+            job.last_error_code = error.code();
+            self.jobs.insert(&job_id, &job);
+
+            self.env().emit_event(ev_payment_failed {
+                job_id,
+                to,
+                amount,
+                error_code: job.last_error_code,
+                payment_retries: job.payment_retries,
+            });
+
+            if job.payment_retries > self.max_payment_retries {
+                if job.status.can_transition_to(&JobStatus::Failed) {
+                    self.transition_job(job_id, JobStatus::Failed)?;
+                    return Ok(false);
+                }
+                //a Cancelled job keeps its dues and waits for requeue_failed_job
+                if job.status == JobStatus::Cancelled {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+
+        ///Clear retry counter and last error once a payment goes through
+        fn register_payment_success(&mut self, job_id: u32) -> Result<(), TreasuryManagerError> {
+            let mut job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+            if job.payment_retries > 0 || job.last_error_code > 0 {
+                job.payment_retries = 0;
+                job.last_error_code = 0;
+                self.jobs.insert(&job_id, &job);
+            }
+            Ok(())
+        }
+
+        ///Nothing left to accrue and nothing left due
+        fn is_fully_paid(job: &JobInfo) -> bool {
+            Self::liability_until(job, u64::MAX) == 0
+                && job.payee_amounts_due.iter().all(|due| *due == 0)
+        }
+
+        ///Move a job that is being paid to PartiallyPaid after its first payment and to Completed once fully paid
+        fn refresh_payment_status(&mut self, job_id: u32) -> Result<(), TreasuryManagerError> {
+            let job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+            if job.status != JobStatus::Pending && job.status != JobStatus::PartiallyPaid {
+                return Ok(());
+            }
+            if Self::is_fully_paid(&job) {
+                self.transition_job(job_id, JobStatus::Completed)?;
+            } else if job.status == JobStatus::Pending && job.total_paid > 0 {
+                self.transition_job(job_id, JobStatus::PartiallyPaid)?;
+            }
+            Ok(())
+        }
+
+        fn _admin_withdrawal(
+            &mut self,
+            amount: Balance,
+            to: AccountId,
+        ) -> Result<(), TreasuryManagerError> {
+            self.make_transfer_to(self.treasury_token_address, to, amount)
+        }
+
+        fn _terminate_me(&mut self) -> Result<(), TreasuryManagerError> {
+            self.env().terminate_contract(self.contract_administrator);
+        }
+
+        fn _propose_manager(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            let deadline = self.env().block_timestamp() + HANDOVER_WINDOW;
+            self.pending_manager = Some(PendingHandover { account, deadline });
+            self.env().emit_event(ev_handover_proposed {
+                role: MANAGER,
+                from: self.contract_manager,
+                to: account,
+                deadline,
+            });
+            Ok(())
+        }
+
+        fn _propose_admin(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            let deadline = self.env().block_timestamp() + HANDOVER_WINDOW;
+            self.pending_admin = Some(PendingHandover { account, deadline });
+            self.env().emit_event(ev_handover_proposed {
+                role: ADMIN,
+                from: self.contract_administrator,
+                to: account,
+                deadline,
+            });
+            Ok(())
+        }
+
+        ///Add approver to the proposal and run its operation once the threshold is reached. Approvals of accounts
+        ///SetApprovers has since removed are dropped and do not count. A failing operation
+        ///leaves the proposal as it was so the approval can be sent again
+        fn record_approval(
+            &mut self,
+            hash: OperationHash,
+            mut proposal: Proposal,
+            approver: AccountId,
+        ) -> Result<(), TreasuryManagerError> {
+            let approvers = &self.approvers;
+            proposal
+                .approvals
+                .retain(|account| approvers.contains(account));
+            proposal.approvals.push(approver);
+            let approvals = proposal.approvals.len() as u8;
+            let executed = approvals >= self.approval_threshold;
+
+            if executed {
+                self.execute_operation(proposal.operation)?;
+                self.proposals.remove(&hash);
+            } else {
+                self.proposals.insert(&hash, &proposal);
+            }
+
+            self.env().emit_event(ev_operation_approved {
+                hash,
+                approver,
+                approvals,
+            });
+            if executed {
+                self.env().emit_event(ev_operation_executed { hash });
+            }
+            Ok(())
+        }
+
+        fn execute_operation(&mut self, operation: Operation) -> Result<(), TreasuryManagerError> {
+            match operation {
+                Operation::AdminWithdrawal { amount, to } => self._admin_withdrawal(amount, to),
+                Operation::TerminateMe => self._terminate_me(),
+                Operation::ProposeManager { account } => self._propose_manager(account),
+                Operation::ProposeAdmin { account } => self._propose_admin(account),
+                Operation::SetOracleDexAddress { oracle_dex_address } => {
+                    self.schedule_change(TreasuryChange::SetOracleDexAddress {
+                        oracle_dex_address,
+                    });
+                    Ok(())
+                }
+                Operation::RegisterForeignAsset {
+                    token_symbol,
+                    token_address,
+                } => self._register_foreign_asset(token_symbol, token_address),
+                Operation::SetApprovers {
+                    approvers,
+                    threshold,
+                } => {
+                    self.check_approvers(&approvers, threshold)?;
+                    self.approvers = approvers;
+                    self.approval_threshold = threshold;
+                    Ok(())
+                }
+                Operation::AddGuardian { account } => {
+                    self._setup_role(GUARDIAN, account);
+                    Ok(())
+                }
+                Operation::RemoveGuardian { account } => {
+                    self._do_revoke_role(GUARDIAN, account);
+                    Ok(())
+                }
+                Operation::SetMinDelay { min_delay } => {
+                    self.schedule_change(TreasuryChange::SetMinDelay { min_delay });
+                    Ok(())
+                }
+            }
+        }
+
+        ///to takes from's seat among the approvers. If to already has one, from's seat goes and
+        ///the threshold comes down to the approvers left so proposals can still pass
+        fn hand_over_approver_seat(&mut self, from: AccountId, to: AccountId) {
+            if self.approvers.contains(&to) {
+                self.approvers.retain(|approver| *approver != from);
+                self.approval_threshold = self.approval_threshold.min(self.approvers.len() as u8);
+            } else if let Some(seat) = self
+                .approvers
+                .iter_mut()
+                .find(|approver| **approver == from)
+            {
+                *seat = to;
+            }
+        }
+
+        fn check_approvers(
+            &self,
+            approvers: &Vec<AccountId>,
+            threshold: u8,
+        ) -> Result<(), TreasuryManagerError> {
+            if threshold == 0
+                || threshold as usize > approvers.len()
+                || !approvers.contains(&self.contract_administrator)
+            {
+                return Err(TreasuryManagerError::InvalidApprovers);
+            }
+            for (position, approver) in approvers.iter().enumerate() {
+                if approvers[position + 1..].contains(approver) {
+                    return Err(TreasuryManagerError::InvalidApprovers);
+                }
+            }
+            Ok(())
         }
 
         #[ink(message)]
@@ -525,8 +1736,94 @@ pub mod treasury_manager {
         }
 
         #[ink(message)]
-        pub fn get_job_info(&self, id: u32) -> JobInfo {
-            self.jobs.get(&id).unwrap()
+        pub fn get_job_info(&self, id: u32) -> Option<JobInfo> {
+            self.jobs.get(&id)
+        }
+
+        ///Page through jobs matching filter. offset is a cursor in the jobs being scanned: the status' ids vector
+        ///if filter.status is set, every job id otherwise. Stops after limit matches or MAX_JOBS_SCANNED jobs and
+        ///returns the offset to continue from, None once the end is reached
+        #[ink(message)]
+        pub fn get_jobs(
+            &self,
+            filter: JobFilter,
+            offset: u32,
+            limit: u32,
+        ) -> (Vec<JobInfo>, Option<u32>) {
+            let candidates_len = match filter.status {
+                Some(status) => self.jobs_vec(status).len() as u32,
+                None => self.next_id,
+            };
+
+            let mut jobs = Vec::new();
+            let mut cursor = offset;
+            while cursor < candidates_len
+                && (jobs.len() as u32) < limit
+                && cursor - offset < MAX_JOBS_SCANNED
+            {
+                let job_id = match filter.status {
+                    Some(status) => self.jobs_vec(status)[cursor as usize],
+                    None => cursor,
+                };
+                cursor += 1;
+
+                //removed jobs leave gaps in the ids
+                if let Some(job) = self.jobs.get(&job_id) {
+                    if Self::job_matches(&job, &filter) {
+                        jobs.push(job);
+                    }
+                }
+            }
+
+            let next_offset = if cursor < candidates_len {
+                Some(cursor)
+            } else {
+                None
+            };
+            (jobs, next_offset)
+        }
+
+        fn job_matches(job: &JobInfo, filter: &JobFilter) -> bool {
+            if filter.status.map_or(false, |status| status != job.status)
+                || filter
+                    .applicant
+                    .map_or(false, |applicant| applicant != job.applicant)
+                || filter
+                    .payee
+                    .map_or(false, |payee| !job.payee_accounts.contains(&payee))
+                || filter
+                    .requested_token
+                    .map_or(false, |token| token != job.requested_token)
+            {
+                return false;
+            }
+            if let Some(payment_type) = &filter.payment_type {
+                if core::mem::discriminant(payment_type)
+                    != core::mem::discriminant(&job.payment_type)
+                {
+                    return false;
+                }
+            }
+            if filter.due_from.is_none() && filter.due_to.is_none() {
+                return true;
+            }
+
+            let due_from = filter.due_from.unwrap_or(0);
+            let due_to = filter.due_to.unwrap_or(u64::MAX);
+            match job.payment_type {
+                PaymentType::Streaming { start, end } => start <= due_to && end >= due_from,
+                _ => (0..job.payment_schedule.len() as u32)
+                    .map(|ts_pointer| Self::instalment_due_timestamp(job, ts_pointer))
+                    .any(|timestamp| timestamp >= due_from && timestamp <= due_to),
+            }
+        }
+
+        fn page_of_ids(ids: &Vec<u32>, offset: u32, limit: u32) -> Vec<u32> {
+            ids.iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect()
         }
 
         #[ink(message)]
@@ -543,16 +1840,51 @@ pub mod treasury_manager {
         }
 
         #[ink(message)]
-        pub fn get_open_jobs_ids(&self) -> Vec<u32> {
-            self.open_jobs_ids.clone()
+        pub fn get_open_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.open_jobs_ids, offset, limit)
+        }
+        #[ink(message)]
+        pub fn get_pending_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.pending_jobs_ids, offset, limit)
+        }
+        #[ink(message)]
+        pub fn get_partially_paid_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.partially_paid_jobs_ids, offset, limit)
+        }
+        #[ink(message)]
+        pub fn get_completed_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.completed_jobs_ids, offset, limit)
+        }
+
+        #[ink(message)]
+        pub fn get_cancelled_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.cancelled_jobs_ids, offset, limit)
+        }
+        #[ink(message)]
+        pub fn get_paused_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.paused_jobs_ids, offset, limit)
         }
+
+        ///Number of jobs with status, for paging through the ids getters
         #[ink(message)]
-        pub fn get_pending_jobs_ids(&self) -> Vec<u32> {
-            self.pending_jobs_ids.clone()
+        pub fn get_jobs_count_by_status(&self, status: JobStatus) -> u32 {
+            self.jobs_vec(status).len() as u32
         }
+
+        ///Up to limit jobs with status, starting at offset in that status' ids vector
         #[ink(message)]
-        pub fn get_completed_jobs_ids(&self) -> Vec<u32> {
-            self.completed_jobs_ids.clone()
+        pub fn get_jobs_by_status(
+            &self,
+            status: JobStatus,
+            offset: u32,
+            limit: u32,
+        ) -> Vec<JobInfo> {
+            self.jobs_vec(status)
+                .iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .filter_map(|job_id| self.jobs.get(job_id))
+                .collect()
         }
 
         #[ink(message)]
@@ -571,624 +1903,927 @@ pub mod treasury_manager {
         }
 
         #[ink(message)]
-        pub fn get_non_native_tokens_vec(&self) -> Vec<AccountId> {
-            self.non_native_tokens_vec.clone()
+        pub fn get_failed_jobs_ids(&self, offset: u32, limit: u32) -> Vec<u32> {
+            Self::page_of_ids(&self.failed_jobs_ids, offset, limit)
         }
 
+        ///KEEPER accounts run the sweeps and payment batches, poke stays open to anyone
         #[ink(message)]
-        pub fn get_liability_thresholds(&self) -> Vec<u8> {
-            self.liabilities_thresholds.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn add_keeper(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            self._setup_role(KEEPER, account);
+            Ok(())
         }
+
         #[ink(message)]
-        pub fn get_liability_health(&self) -> Vec<u8> {
-            self.liability_health.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn remove_keeper(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            self._do_revoke_role(KEEPER, account);
+            Ok(())
         }
+
+        ///TREASURER accounts can swap treasury tokens through execute_swap
         #[ink(message)]
-        pub fn get_liability_in_usdt_tokens_treasury(&self) -> Vec<Balance> {
-            self.liability_in_usdt_tokens_treasury.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn add_treasurer(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            self._setup_role(TREASURER, account);
+            Ok(())
         }
+
         #[ink(message)]
-        pub fn get_liability_in_usdt_tokens(&self) -> Vec<Balance> {
-            self.liability_in_usdt_tokens.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn remove_treasurer(&mut self, account: AccountId) -> Result<(), TreasuryManagerError> {
+            self._do_revoke_role(TREASURER, account);
+            Ok(())
         }
+
         #[ink(message)]
-        pub fn get_liability_in_treasury(&self) -> Vec<Balance> {
-            self.liability_in_treasury.clone()
+        pub fn get_keeper_settings(&self) -> (u32, Balance, Balance, u64) {
+            (
+                self.keeper_batch_size,
+                self.keeper_bounty,
+                self.keeper_bounty_cap,
+                self.keeper_bounty_period,
+            )
         }
+
+        ///Items poke processes per sweep and the bounty it pays, at most bounty_cap every bounty_period ms
         #[ink(message)]
-        pub fn get_check_points_intervals(&self) -> Vec<u64> {
-            self.check_points_intervals.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_keeper_settings(
+            &mut self,
+            batch_size: u32,
+            bounty: Balance,
+            bounty_cap: Balance,
+            bounty_period: u64,
+        ) -> Result<(), TreasuryManagerError> {
+            self.keeper_batch_size = batch_size;
+            self.keeper_bounty = bounty;
+            self.keeper_bounty_cap = bounty_cap;
+            self.keeper_bounty_period = bounty_period;
+            Ok(())
         }
 
+        ///Cursors of check_open_jobs, check_pending_jobs and the native, native usd and non native payment queues
         #[ink(message)]
-        pub fn get_foreign_assets_vec(&self) -> Vec<String> {
-            self.foreign_assets_vec.clone()
+        pub fn get_sweep_cursors(&self) -> Vec<u32> {
+            let mut cursors = vec![self.open_jobs_cursor, self.pending_jobs_cursor];
+            cursors.extend(self.payment_queue_cursors.iter());
+            cursors
         }
 
         #[ink(message)]
-        pub fn get_foreign_asset_account(&self, symbol: String) -> AccountId {
-            self.foreign_assets.get(&symbol).unwrap_or_default()
+        pub fn get_max_price_age(&self) -> u64 {
+            self.max_price_age
         }
 
+        ///Schedule the oldest oracle price in ms that payments and liabilities are calculated from.
+        ///execute_change applies it once the timelock delay has passed
         #[ink(message)]
-        pub fn get_oracle_dex_address(&self) -> AccountId {
-            self.oracle_dex_address.clone()
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_max_price_age(
+            &mut self,
+            max_price_age: u64,
+        ) -> Result<(), TreasuryManagerError> {
+            self.schedule_change(TreasuryChange::SetMaxPriceAge { max_price_age });
+            Ok(())
         }
 
-        //FOR TESTING ONLY AS IT CAN BE FOUND DIRECTLY FROM PSP22
         #[ink(message)]
-        pub fn get_balance(&self, token_address: AccountId, account: AccountId) -> Balance {
-            PSP22Ref::balance_of(&token_address, account)
+        pub fn get_swap_tolerance_bps(&self) -> u16 {
+            self.swap_tolerance_bps
         }
 
+        ///Schedule the extra treasury tokens, in basis points, swapped for a non native payment to absorb slippage.
+        ///execute_change applies it once the timelock delay has passed
         #[ink(message)]
-        pub fn make_native_payments(&mut self) -> Result<(), AccessControlError> {
-            let mut new_native_payments_ids = Vec::new();
-
-            for job_id in self.native_payments_ids.clone() {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
-                let requested_value = current_job.requested_value;
-                let payee_accounts = current_job.payee_accounts;
-
-                match self.make_transfer_to(
-                    self.treasury_token_address,
-                    payee_accounts[0],
-                    requested_value,
-                ) {
-                    Ok(()) => {
-                        ink_env::debug_println!(
-                            "NATIVE PAYMENT with id: {} has succeeded",
-                            current_job.id
-                        );
-
-                        self.env().emit_event(ev_native_payment {
-                            job_id: job_id,
-                            to: payee_accounts[0],
-                            amount: requested_value,
-                        });
-                    }
-                    _ => {
-                        new_native_payments_ids.push(job_id);
-                        ink_env::debug_println!(
-                            "NATIVE PAYMENT with id: {} has failed",
-                            current_job.id
-                        );
-                    }
-                }
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_swap_tolerance_bps(
+            &mut self,
+            swap_tolerance_bps: u16,
+        ) -> Result<(), TreasuryManagerError> {
+            if swap_tolerance_bps as u128 >= BPS {
+                return Err(TreasuryManagerError::InvalidSwapTolerance);
             }
-            self.native_payments_ids = new_native_payments_ids;
-
+            self.schedule_change(TreasuryChange::SetSwapToleranceBps { swap_tolerance_bps });
             Ok(())
         }
 
-        //For MANAGER ONLY
         #[ink(message)]
-        // #[modifiers(only_role(ADMIN, MANAGER))]
-        pub fn make_native_usd_payments(&mut self) -> Result<(), AccessControlError> {
-            // self.native_payments_usd_ids.push(current_job.id);
-
-            //GET ORACLE PRICE FOR DOT/USDT
-            let price = self.get_average_price_for_pair(
-                self.treasury_token_address,
-                self.foreign_assets.get(&String::from("USDT")).unwrap(),
-            );
-
-            let mut new_native_usd_payments_ids = Vec::new();
-
-            for job_id in self.native_payments_usd_ids.clone() {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
-                let requested_value = current_job.requested_value; //this is USDT value in this case
-                let amount = requested_value / price;
+        pub fn get_max_payment_retries(&self) -> u8 {
+            self.max_payment_retries
+        }
 
-                let payee_accounts = current_job.payee_accounts;
+        ///Number of failed attempts after which a job is parked as Failed
+        #[ink(message)]
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_max_payment_retries(
+            &mut self,
+            max_payment_retries: u8,
+        ) -> Result<(), TreasuryManagerError> {
+            self.max_payment_retries = max_payment_retries;
+            Ok(())
+        }
 
-                match self.make_transfer_to(self.treasury_token_address, payee_accounts[0], amount)
-                {
-                    Ok(()) => {
-                        ink_env::debug_println!(
-                            "NATIVE USD PAYMENT with id: {} and amount: {} has succeeded",
-                            current_job.id,
-                            amount
-                        );
+        ///Take a Failed job back to Pending, or PartiallyPaid if it was paid before, reset its retries and push it back in its payment queue.
+        ///A Cancelled job whose dues ran out of retries goes back in its payment queue
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_role(MANAGER))]
+        pub fn requeue_failed_job(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            let mut job: JobInfo = self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            let cancelled_with_dues = job.status == JobStatus::Cancelled
+                && job.payee_amounts_due.iter().any(|due| *due > 0);
+            if job.status != JobStatus::Failed && !cancelled_with_dues {
+                return Err(TreasuryManagerError::JobNotFailed);
+            }
+            job.payment_retries = 0;
+            self.jobs.insert(&id, &job);
 
-                        self.env().emit_event(ev_native_usd_payment {
-                            job_id: job_id,
-                            to: payee_accounts[0],
-                            amount: amount,
-                        });
-                    }
-                    _ => {
-                        new_native_usd_payments_ids.push(job_id);
-                        ink_env::debug_println!(
-                            "NATIVE USD PAYMENT with id: {} has failed",
-                            current_job.id
-                        );
-                    }
-                }
+            if cancelled_with_dues {
+                self.push_to_payment_queue(&job);
+                return Ok(());
             }
-            self.native_payments_usd_ids = new_native_usd_payments_ids;
+
+            let to = if job.total_paid > 0 {
+                JobStatus::PartiallyPaid
+            } else {
+                JobStatus::Pending
+            };
+            self.transition_job(id, to)?;
+
+            let job: JobInfo = self.jobs.get(&id).ok_or(TreasuryManagerError::UnknownJob)?;
+            self.queue_payment(&job);
 
             Ok(())
         }
 
-        //For MANAGER ONLY
         #[ink(message)]
-        // #[modifiers(only_role(ADMIN, MANAGER))]
-        pub fn make_non_native_payments(&mut self) -> Result<(), AccessControlError> {
-            let mut new_non_native_payments_ids = Vec::new();
-
-            for job_id in self.non_native_payments_ids.clone() {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
+        pub fn get_non_native_tokens_vec(&self) -> Vec<AccountId> {
+            self.non_native_tokens_vec.clone()
+        }
 
-                //this is value in non native tokens (in this case we cover USDT)
-                //and treasury tokens will be swapped for non_native tokens (USDT) and sent to the payee
-                let requested_value = current_job.requested_value;
-                //we asssume that in DEX all pairs are Token/USDT.
+        #[ink(message)]
+        pub fn get_liability_horizons(&self) -> Vec<LiabilityHorizon> {
+            self.liability_horizons.clone()
+        }
+        ///One report per horizon, in the order of liability_horizons
+        #[ink(message)]
+        pub fn get_liability_reports(&self) -> Vec<LiabilityReport> {
+            self.liability_reports.clone()
+        }
 
-                //GET ORACLE PRICE FOR DOT/USDT
-                let usdt_address = self.foreign_assets.get(&String::from("USDT")).unwrap();
-                let price = self.get_price_for_pair(self.treasury_token_address, usdt_address);
+        #[ink(message)]
+        pub fn get_foreign_assets_vec(&self) -> Vec<String> {
+            self.foreign_assets_vec.clone()
+        }
 
-                let amount = requested_value / price;
-                let use_average_price = false;
+        #[ink(message)]
+        pub fn get_foreign_asset_account(&self, symbol: String) -> AccountId {
+            self.foreign_assets.get(&symbol).unwrap_or_default()
+        }
 
-                let payee_accounts = current_job.payee_accounts;
+        #[ink(message)]
+        pub fn get_oracle_dex_address(&self) -> AccountId {
+            self.oracle_dex_address.clone()
+        }
 
-                self.execute_swap(
-                    self.treasury_token_address,
-                    usdt_address,
-                    amount,
-                    use_average_price,
-                );
+        //FOR TESTING ONLY AS IT CAN BE FOUND DIRECTLY FROM PSP22
+        #[ink(message)]
+        pub fn get_balance(&self, token_address: AccountId, account: AccountId) -> Balance {
+            PSP22Ref::balance_of(&token_address, account)
+        }
 
-                match self.make_transfer_to(usdt_address, payee_accounts[0], requested_value) {
-                    Ok(()) => {
-                        ink_env::debug_println!(
-                            "NON NATIVE PAYMENT with id: {} and requested_value in foreign asset: {} used treasury tokens amount: {} has succeeded",
-                            current_job.id,
-                            requested_value,
-                            amount,
-                        );
+        fn treasury_balance(&self) -> Balance {
+            PSP22Ref::balance_of(&self.treasury_token_address, self.env().account_id())
+        }
 
-                        //EVENT
-                        self.env().emit_event(ev_non_native_payment {
-                            job_id: job_id,
-                            to: payee_accounts[0],
-                            amount: amount,
-                        });
-                    }
-                    _ => {
-                        new_non_native_payments_ids.push(job_id);
-                        ink_env::debug_println!(
-                            "NON NATIVE PAYMENT with id: {} has failed",
-                            current_job.id
-                        );
-                    }
-                }
-            }
-            self.non_native_payments_ids = new_non_native_payments_ids;
+        #[ink(message)]
+        #[modifiers(when_not_paused, only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn make_native_payments(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            self._make_native_payments(max_items)
+        }
 
-            Ok(())
+        fn _make_native_payments(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            //price is not needed for payments in treasury tokens
+            self.process_payment_queue(PaymentQueue::Native, Price::default(), max_items)
         }
 
         #[ink(message)]
-        #[modifiers(only_role(MANAGER))]
-        pub fn set_liabilities_thresholds(
+        #[modifiers(when_not_paused, only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn make_native_usd_payments(
             &mut self,
-            liabilities_threshold_level1: u8,
-            // liabilities_threshold_level2: u8,
-        ) -> Result<(), AccessControlError> {
-            // assert!(
-            //     liabilities_threshold_level1 < liabilities_threshold_level2,
-            //     "liabilities_threshold_level1 must be lower than liabilities_threshold_level2"
-            // );
-            self.liabilities_thresholds = vec![
-                (100 - liabilities_threshold_level1),
-                (100 - liabilities_threshold_level1 * 2),
-                (100 + liabilities_threshold_level1 * 3),
-            ];
-            Ok(())
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            self._make_native_usd_payments(max_items)
         }
 
-        #[ink(message)]
-        #[modifiers(only_role(MANAGER))]
-        pub fn set_check_points_intervals(
+        fn _make_native_usd_payments(
             &mut self,
-            checkpoint1: u64,
-            checkpoint2: u64,
-            checkpoint3: u64,
-        ) -> Result<(), AccessControlError> {
-            self.check_points_intervals = vec![checkpoint1, checkpoint2, checkpoint3];
-            Ok(())
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            //GET ORACLE PRICE FOR DOT/USDT
+            let price = self.usdt_price(true)?;
+            self.process_payment_queue(PaymentQueue::NativeUsd, price, max_items)
         }
 
-        //For MANAGER ONLY
         #[ink(message)]
-        // #[modifiers(only_role(ADMIN, MANAGER))]
-        pub fn calculate_liabilities(&mut self) -> Result<(), AccessControlError> {
-            let treasury_tokens_balance = PSP22Ref::balance_of(
-                &self.treasury_token_address,
-                self.env().account_id().clone(),
-            );
+        #[modifiers(when_not_paused, only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn make_non_native_payments(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            self._make_non_native_payments(max_items)
+        }
 
-            ink_env::debug_println!(
-                "calculate_liabilities treasury_tokens_balance: {}",
-                treasury_tokens_balance
-            );
+        fn _make_non_native_payments(
+            &mut self,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            //every job is priced against its own requested_token
+            self.process_payment_queue(PaymentQueue::NonNative, Price::default(), max_items)
+        }
 
-            let current_timestamp = self.env().block_timestamp();
-            // let current_timestamp = self.fake_timestamp;
+        ///Anyone can call. Moves due open jobs to pending, accrues and pays what is due, recomputes liabilities
+        ///and pays the caller keeper_bounty in treasury tokens if a job changed status, accrued or was paid
+        ///and the period cap allows. Queues priced by the oracle and liabilities are left for later
+        ///while it has no fresh price, and recomputing liabilities alone earns no bounty. Returns the bounty paid
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        pub fn poke(&mut self) -> Result<Balance, TreasuryManagerError> {
+            let batch_size = self.keeper_batch_size;
+            let mut completed = self._check_open_jobs(batch_size)?.completed;
+            completed += self._check_pending_jobs(batch_size)?.completed;
+            completed += self._make_native_payments(batch_size)?.completed;
+
+            if !self.native_payments_usd_ids.is_empty() {
+                match self._make_native_usd_payments(batch_size) {
+                    Ok(progress) => completed += progress.completed,
+                    Err(TreasuryManagerError::OracleUnavailable)
+                    | Err(TreasuryManagerError::StalePrice) => (),
+                    Err(error) => return Err(error),
+                }
+            }
+            if !self.non_native_payments_ids.is_empty() {
+                match self._make_non_native_payments(batch_size) {
+                    Ok(progress) => completed += progress.completed,
+                    Err(TreasuryManagerError::OracleUnavailable)
+                    | Err(TreasuryManagerError::StalePrice) => (),
+                    Err(error) => return Err(error),
+                }
+            }
+            match self._calculate_liabilities() {
+                Ok(()) => (),
+                Err(TreasuryManagerError::OracleUnavailable)
+                | Err(TreasuryManagerError::StalePrice) => (),
+                Err(error) => return Err(error),
+            }
 
-            let timesstamp_2D = current_timestamp + self.check_points_intervals[0];
-            let timesstamp_7D = current_timestamp + self.check_points_intervals[1];
-            let timesstamp_30D = current_timestamp + self.check_points_intervals[2];
+            if completed == 0 {
+                return Ok(0);
+            }
+            Ok(self.pay_keeper_bounty(self.env().caller()))
+        }
 
-            ink_env::debug_println!(
-                "calculate_liabilities current_timestamp: {} timesstamp_2D: {} timesstamp_7D: {} timesstamp_30D: {}",
-                current_timestamp,timesstamp_2D,timesstamp_7D,timesstamp_30D
+        ///Bounty left in the current period, paid if the treasury can afford it. A failed bounty does not undo the poke
+        fn pay_keeper_bounty(&mut self, keeper: AccountId) -> Balance {
+            let now = self.env().block_timestamp();
+            if now >= self.keeper_period_start + self.keeper_bounty_period {
+                self.keeper_period_start = now;
+                self.keeper_paid_in_period = 0;
+            }
+
+            let amount = self.keeper_bounty.min(
+                self.keeper_bounty_cap
+                    .saturating_sub(self.keeper_paid_in_period),
             );
+            if amount == 0
+                || self
+                    .make_transfer_to(self.treasury_token_address, keeper, amount)
+                    .is_err()
+            {
+                return 0;
+            }
+            self.keeper_paid_in_period += amount;
 
-            //Keeps hold of payments in treasury tokens e.g. DOT
-            let mut liability_in_treasury: Balance = 0;
-            let mut liability_in_treasury_2D: Balance = 0;
-            let mut liability_in_treasury_7D: Balance = 0;
-            let mut liability_in_treasury_30D: Balance = 0;
-
-            //Keeps hold of payments in USDT
-            let mut liability_in_usdt_tokens: Balance = 0;
-            let mut liability_in_usdt_2D: Balance = 0;
-            let mut liability_in_usdt_7D: Balance = 0;
-            let mut liability_in_usdt_30D: Balance = 0;
-            //Keeps hold of the above payments in USDT and the DOT equivalent
-            let mut liability_in_usdt_tokens_treasury: Balance = 0;
-            let mut liability_in_usdt_2D_treasury: Balance = 0;
-            let mut liability_in_usdt_7D_treasury: Balance = 0;
-            let mut liability_in_usdt_30D_treasury: Balance = 0;
-
-            let usdt_address = self.foreign_assets.get(&String::from("USDT")).unwrap();
-            let price = self.get_price_for_pair(self.treasury_token_address, usdt_address);
+            self.env().emit_event(ev_keeper_rewarded { keeper, amount });
 
-            ink_env::debug_println!(
-                "calculate_liabilities price: {} usdt_address: {:?}",
-                price,
-                usdt_address
-            );
+            amount
+        }
 
-            for job_id in self.open_jobs_ids.clone() {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
+        ///Settle up to max_items jobs of the queue starting at its cursor. Paid and parked jobs leave the queue,
+        ///jobs with failed legs stay for the next round
+        fn process_payment_queue(
+            &mut self,
+            which: PaymentQueue,
+            price: Price,
+            max_items: u32,
+        ) -> Result<BatchProgress, TreasuryManagerError> {
+            let mut processed: u32 = 0;
+            let mut completed: u32 = 0;
+
+            //the stored cursor is read on every step as removals move it back
+            while processed < max_items {
+                let cursor = self.payment_queue_cursors[which as usize] as usize;
+                let job_id = match self.payment_queue_mut(which).get(cursor) {
+                    Some(job_id) => *job_id,
+                    None => break,
+                };
+                processed += 1;
+                let paid_before = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?
+                    .total_paid;
+
+                let stays_queued = match self.settle_job_payment(job_id, price) {
+                    Ok(stays_queued) => stays_queued,
+                    //nothing is paid from a missing or stale price, the job waits for a fresh one
+                    Err(TreasuryManagerError::OracleUnavailable)
+                    | Err(TreasuryManagerError::StalePrice) => true,
+                    Err(error) => return Err(error),
+                };
 
-                if current_job.requested_token == self.treasury_token_address
-                    && !current_job.value_in_usd
-                {
-                    for ts_pointer in current_job.next_installment_pointer
-                        ..(current_job.payment_schedule.len() as u32)
-                    {
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_2D {
-                            liability_in_treasury_2D += current_job.requested_value;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_7D {
-                            liability_in_treasury_7D += current_job.requested_value;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_30D {
-                            liability_in_treasury_30D += current_job.requested_value;
-                        }
-                        liability_in_treasury += current_job.requested_value
-                    }
+                //only payments that went through count as work
+                let paid_after = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?
+                    .total_paid;
+                if paid_after > paid_before {
+                    completed += 1;
+                }
+
+                if !stays_queued {
+                    self.remove_from_payment_queue(which, job_id);
                 } else {
-                    for ts_pointer in current_job.next_installment_pointer
-                        ..(current_job.payment_schedule.len() as u32)
-                    {
-                        let amount_in_treasury_tokens = current_job.requested_value / price;
-                        let usdt_value = current_job.requested_value;
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_2D {
-                            liability_in_usdt_2D += usdt_value;
-                            liability_in_usdt_2D_treasury += amount_in_treasury_tokens;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_7D {
-                            liability_in_usdt_7D += usdt_value;
-                            liability_in_usdt_7D_treasury += amount_in_treasury_tokens;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_30D {
-                            liability_in_usdt_30D += usdt_value;
-                            liability_in_usdt_30D_treasury += amount_in_treasury_tokens;
-                        }
-                        liability_in_usdt_tokens += usdt_value;
-                        liability_in_usdt_tokens_treasury += amount_in_treasury_tokens;
+                    let cursor = self.payment_queue_cursors[which as usize] as usize;
+                    if self.payment_queue_mut(which).get(cursor) == Some(&job_id) {
+                        self.payment_queue_cursors[which as usize] += 1;
                     }
                 }
             }
 
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_treasury: {} liability_in_treasury_2D: {} liability_in_treasury_7D: {} liability_in_treasury_30D: {}",
-                liability_in_treasury,liability_in_treasury_2D,liability_in_treasury_7D,liability_in_treasury_30D
-            );
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_usdt_tokens: {} liability_in_usdt_2D: {} liability_in_usdt_7D: {} liability_in_usdt_30D: {}",
-                liability_in_usdt_tokens,liability_in_usdt_2D,liability_in_usdt_7D,liability_in_usdt_30D
-            );
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_usdt_tokens_treasury: {} liability_in_usdt_2D_treasury: {} liability_in_usdt_7D_treasury: {} liability_in_usdt_30D_treasury: {}",
-                liability_in_usdt_tokens_treasury,liability_in_usdt_2D_treasury,liability_in_usdt_7D_treasury,liability_in_usdt_30D_treasury
+            let cursor = self.payment_queue_cursors[which as usize] as usize;
+            let progress = Self::batch_progress(
+                processed,
+                completed,
+                cursor,
+                self.payment_queue_mut(which).len(),
             );
+            self.payment_queue_cursors[which as usize] = if progress.remaining == 0 {
+                0
+            } else {
+                cursor as u32
+            };
+
+            Ok(progress)
+        }
+
+        fn payment_queue_mut(&mut self, which: PaymentQueue) -> &mut Vec<u32> {
+            match which {
+                PaymentQueue::Native => &mut self.native_payments_ids,
+                PaymentQueue::NativeUsd => &mut self.native_payments_usd_ids,
+                PaymentQueue::NonNative => &mut self.non_native_payments_ids,
+            }
+        }
+
+        ///Payee withdraws what a Streaming job has accrued since the last claim.
+        ///The accrued amount is split across all payee_accounts
+        #[ink(message)]
+        #[modifiers(when_not_paused)]
+        pub fn claim_stream(&mut self, job_id: u32) -> Result<Balance, TreasuryManagerError> {
+            let current_job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+            if !matches!(current_job.payment_type, PaymentType::Streaming { .. }) {
+                return Err(TreasuryManagerError::NotStreamingJob);
+            }
+            if !current_job.payee_accounts.contains(&self.env().caller()) {
+                return Err(TreasuryManagerError::NotPayee);
+            }
+            if !matches!(
+                current_job.status,
+                JobStatus::Open | JobStatus::Pending | JobStatus::PartiallyPaid
+            ) {
+                return Err(TreasuryManagerError::InvalidJobState);
+            }
+
+            let accrued = Self::streamed_until(&current_job, self.env().block_timestamp())
+                .saturating_sub(current_job.streamed_amount);
+            if accrued == 0 {
+                return Err(TreasuryManagerError::NothingToClaim);
+            }
+
+            //non native jobs are priced against their requested_token when paid.
+            //Either price must be fresh before anything accrues
+            let price = if current_job.requested_token != self.treasury_token_address {
+                self.token_price(current_job.requested_token, false)?
+            } else if current_job.value_in_usd {
+                self.usdt_price(true)?
+            } else {
+                Price::default()
+            };
+
+            let updated_job = JobInfo {
+                streamed_amount: current_job.streamed_amount + accrued,
+                ..current_job
+            };
+            let updated_job = self.accrue_payment(updated_job, accrued);
+
+            if updated_job.status == JobStatus::Open {
+                self.transition_job(job_id, JobStatus::Pending)?;
+            }
+
+            //legs that fail stay due and are retried with the rest of the payment queue
+            if self.settle_job_payment(job_id, price)? {
+                let current_job: JobInfo = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+                self.queue_payment(&current_job);
+            }
 
-            for job_id in self.pending_jobs_ids.clone() {
-                let current_job: JobInfo = self.jobs.get(&job_id).unwrap();
+            Ok(accrued)
+        }
+
+        ///DOT/USDT price from the oracle, last or average. Fails if the oracle has no price
+        fn usdt_price(&self, use_average_price: bool) -> Result<Price, TreasuryManagerError> {
+            self.token_price(self.usdt_address()?, use_average_price)
+        }
+
+        fn usdt_address(&self) -> Result<AccountId, TreasuryManagerError> {
+            self.foreign_assets
+                .get(&String::from("USDT"))
+                .ok_or(TreasuryManagerError::UnregisteredToken)
+        }
+
+        ///Price of token in treasury tokens e.g. DOT/USDC, last or average, along the token's payment route.
+        ///Fails if the oracle has no price for a pair of the route or it is older than max_price_age
+        fn token_price(
+            &self,
+            token: AccountId,
+            use_average_price: bool,
+        ) -> Result<Price, TreasuryManagerError> {
+            let price = OracleDexRef::get_route_price_checked(
+                &self.oracle_dex_address,
+                self.payment_route(token),
+                use_average_price,
+                self.max_price_age,
+            )?;
+            Ok(price)
+        }
+
+        ///Path treasury tokens are swapped along to pay in token
+        fn payment_route(&self, token: AccountId) -> Vec<AccountId> {
+            self.payment_routes
+                .get(&token)
+                .unwrap_or_else(|| vec![self.treasury_token_address, token])
+        }
+
+        fn token_decimals(&self, token: AccountId) -> u8 {
+            PSP22MetadataRef::token_decimals(&token)
+        }
+
+        ///Treasury tokens worth amount of a token priced at price treasury/token
+        fn in_treasury_tokens(
+            amount: Balance,
+            price: &Price,
+            treasury_decimals: u8,
+            token_decimals: u8,
+            rounding: Rounding,
+        ) -> Result<Balance, TreasuryManagerError> {
+            price
+                .base_amount(amount, treasury_decimals, token_decimals, rounding)
+                .ok_or(TreasuryManagerError::PriceConversionFailed)
+        }
+
+        ///Pay the job's dues and record the outcome. Returns true if the job should stay in its payment queue
+        fn settle_job_payment(
+            &mut self,
+            job_id: u32,
+            price: Price,
+        ) -> Result<bool, TreasuryManagerError> {
+            let failure = self.pay_job_dues(job_id, price)?;
+            self.refresh_payment_status(job_id)?;
 
-                if current_job.requested_token == self.treasury_token_address
-                    && !current_job.value_in_usd
+            match failure {
+                None => {
+                    self.register_payment_success(job_id)?;
+                    Ok(false)
+                }
+                Some((payee, amount, error)) => {
+                    self.register_payment_failure(job_id, payee, amount, error)
+                }
+            }
+        }
+
+        ///Pay every payee leg still due on the job. price is DOT/USDT for USD valued jobs and is ignored otherwise,
+        ///non native jobs are priced against their own requested_token. Treasury tokens swapped for a non native
+        ///payment cover requested_value plus swap_tolerance_bps, rounded up, and the swap must deliver at least
+        ///requested_value; anything above stays in the treasury. What a swap delivered is recorded on the leg until
+        ///its transfer goes through, so a retry pays from it instead of swapping again. USD valued payments are
+        ///rounded down. Paid legs are cleared so a retry only pays the ones that failed. Returns the first failed leg
+        fn pay_job_dues(
+            &mut self,
+            job_id: u32,
+            price: Price,
+        ) -> Result<Option<(AccountId, Balance, TreasuryManagerError)>, TreasuryManagerError>
+        {
+            let mut current_job: JobInfo = self
+                .jobs
+                .get(&job_id)
+                .ok_or(TreasuryManagerError::UnknownJob)?;
+            let mut failure = None;
+
+            //a missing or stale price stops the payment before anything is paid or counted as a failure
+            let price = if current_job.requested_token != self.treasury_token_address {
+                self.token_price(current_job.requested_token, false)?
+            } else {
+                price
+            };
+
+            for (leg, payee) in current_job.payee_accounts.clone().iter().enumerate() {
+                let requested_value = current_job.payee_amounts_due[leg];
+                if requested_value == 0 {
+                    continue;
+                }
+
+                let transfer_result = if current_job.requested_token != self.treasury_token_address
                 {
-                    for ts_pointer in current_job.next_installment_pointer
-                        ..(current_job.payment_schedule.len() as u32)
-                    {
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_2D {
-                            liability_in_treasury_2D += current_job.requested_value;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_7D {
-                            liability_in_treasury_7D += current_job.requested_value;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_30D {
-                            liability_in_treasury_30D += current_job.requested_value;
+                    //this is value in non native tokens e.g. USDT, USDC
+                    //and treasury tokens will be swapped for the job's requested_token and sent to the payee
+                    let requested_token = current_job.requested_token;
+                    let (swapped, swap_cost) = current_job
+                        .payee_amounts_swapped
+                        .get(leg)
+                        .cloned()
+                        .unwrap_or_default();
+
+                    //a leg whose transfer failed after its swap is paid from what that swap delivered,
+                    //only a shortfall, e.g. from dues accrued since, is swapped
+                    let swap_result = if swapped >= requested_value {
+                        Ok((swapped, swap_cost))
+                    } else {
+                        let shortfall = requested_value - swapped;
+                        mul_div(
+                            shortfall,
+                            BPS + self.swap_tolerance_bps as u128,
+                            BPS,
+                            Rounding::Up,
+                        )
+                        .ok_or(TreasuryManagerError::PriceConversionFailed)
+                        .and_then(|expected_out| {
+                            Self::in_treasury_tokens(
+                                expected_out,
+                                &price,
+                                self.token_decimals(self.treasury_token_address),
+                                self.token_decimals(requested_token),
+                                Rounding::Up,
+                            )
+                        })
+                        .and_then(|amount| {
+                            self._execute_swap(
+                                self.payment_route(requested_token),
+                                amount,
+                                shortfall,
+                                self.env().block_timestamp(),
+                            )
+                            .map(|delivered| (swapped + delivered, swap_cost + amount))
+                        })
+                    };
+
+                    swap_result.and_then(|(swapped, swap_cost)| {
+                        //kept until the transfer goes through so a retry does not swap again
+                        if current_job.payee_amounts_swapped.len()
+                            != current_job.payee_accounts.len()
+                        {
+                            current_job.payee_amounts_swapped =
+                                vec![(0, 0); current_job.payee_accounts.len()];
                         }
-                        liability_in_treasury += current_job.requested_value
-                    }
+                        current_job.payee_amounts_swapped[leg] = (swapped, swap_cost);
+                        self.make_transfer_to(requested_token, *payee, requested_value)?;
+                        current_job.payee_amounts_swapped[leg] = (0, 0);
+                        Ok(swap_cost)
+                    })
+                } else if current_job.value_in_usd {
+                    //requested_value is USDT value in this case
+                    self.usdt_address()
+                        .and_then(|usdt_address| {
+                            Self::in_treasury_tokens(
+                                requested_value,
+                                &price,
+                                self.token_decimals(self.treasury_token_address),
+                                self.token_decimals(usdt_address),
+                                Rounding::Down,
+                            )
+                        })
+                        .and_then(|amount| {
+                            self.make_transfer_to(self.treasury_token_address, *payee, amount)
+                                .map(|_| amount)
+                        })
                 } else {
-                    for ts_pointer in current_job.next_installment_pointer
-                        ..(current_job.payment_schedule.len() as u32)
-                    {
-                        let amount_in_treasury_tokens = current_job.requested_value / price;
-                        let usdt_value = current_job.requested_value;
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_2D {
-                            liability_in_usdt_2D += usdt_value;
-                            liability_in_usdt_2D_treasury += amount_in_treasury_tokens;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_7D {
-                            liability_in_usdt_7D += usdt_value;
-                            liability_in_usdt_7D_treasury += amount_in_treasury_tokens;
-                        }
-                        if current_job.payment_schedule[ts_pointer as usize] <= timesstamp_30D {
-                            liability_in_usdt_30D += usdt_value;
-                            liability_in_usdt_30D_treasury += amount_in_treasury_tokens;
+                    self.make_transfer_to(self.treasury_token_address, *payee, requested_value)
+                        .map(|_| requested_value)
+                };
+
+                match transfer_result {
+                    Ok(amount) => {
+                        ink_env::debug_println!(
+                            "PAYMENT with id: {} to payee: {:?} and requested_value: {} used treasury tokens amount: {} has succeeded",
+                            current_job.id,
+                            payee,
+                            requested_value,
+                            amount,
+                        );
+
+                        current_job.payee_amounts_due[leg] = 0;
+                        current_job.total_paid += requested_value;
+                        self.emit_payment_event(&current_job, *payee, amount);
+                    }
+                    Err(error) => {
+                        ink_env::debug_println!(
+                            "PAYMENT with id: {} to payee: {:?} has failed",
+                            current_job.id,
+                            payee
+                        );
+                        if failure.is_none() {
+                            failure = Some((*payee, requested_value, error));
                         }
-                        liability_in_usdt_tokens += usdt_value;
-                        liability_in_usdt_tokens_treasury += amount_in_treasury_tokens;
                     }
                 }
             }
+            self.jobs.insert(&job_id, &current_job);
 
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_treasury: {} liability_in_treasury_2D: {} liability_in_treasury_7D: {} liability_in_treasury_30D: {}",
-                liability_in_treasury,liability_in_treasury_2D,liability_in_treasury_7D,liability_in_treasury_30D
-            );
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_usdt_tokens: {} liability_in_usdt_2D: {} liability_in_usdt_7D: {} liability_in_usdt_30D: {}",
-                liability_in_usdt_tokens,liability_in_usdt_2D,liability_in_usdt_7D,liability_in_usdt_30D
-            );
-            ink_env::debug_println!(
-                "calculate_liabilities liability_in_usdt_tokens_treasury: {} liability_in_usdt_2D_treasury: {} liability_in_usdt_7D_treasury: {} liability_in_usdt_30D_treasury: {}",
-                liability_in_usdt_tokens_treasury,liability_in_usdt_2D_treasury,liability_in_usdt_7D_treasury,liability_in_usdt_30D_treasury
-            );
+            Ok(failure)
+        }
+
+        ///One event per paid leg. amount is in treasury tokens
+        fn emit_payment_event(&self, job: &JobInfo, to: AccountId, amount: Balance) {
+            if job.requested_token != self.treasury_token_address {
+                self.env().emit_event(ev_non_native_payment {
+                    job_id: job.id,
+                    to,
+                    amount,
+                });
+            } else if job.value_in_usd {
+                self.env().emit_event(ev_native_usd_payment {
+                    job_id: job.id,
+                    to,
+                    amount,
+                });
+            } else {
+                self.env().emit_event(ev_native_payment {
+                    job_id: job.id,
+                    to,
+                    amount,
+                });
+            }
+        }
+
+        ///Schedule replacing the horizons calculate_liabilities reports on. Once execute_change applies it,
+        ///reports are cleared until the next calculation
+        #[ink(message)]
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_liability_horizons(
+            &mut self,
+            liability_horizons: Vec<LiabilityHorizon>,
+        ) -> Result<(), TreasuryManagerError> {
+            if liability_horizons.is_empty()
+                || liability_horizons.len() > MAX_LIABILITY_HORIZONS
+                || liability_horizons.iter().any(|horizon| {
+                    horizon.critical <= horizon.warning
+                        || horizon.critical > 100
+                        || horizon.top_up_target < 100
+                })
+            {
+                return Err(TreasuryManagerError::InvalidThresholds);
+            }
+            self.schedule_change(TreasuryChange::SetLiabilityHorizons { liability_horizons });
+            Ok(())
+        }
 
-            let threshold_1 =
-                (treasury_tokens_balance * (self.liabilities_thresholds[0 as usize]) as u128) / 100;
-            let threshold_2 =
-                (treasury_tokens_balance * (self.liabilities_thresholds[1 as usize]) as u128) / 100;
+        ///Apply a scheduled change once its delay has passed. Anyone can call it
+        #[ink(message)]
+        pub fn execute_change(&mut self, id: u32) -> Result<(), TreasuryManagerError> {
+            let call = self._take_ready_change(id)?;
+            let change = <TreasuryChange as scale::Decode>::decode(&mut &call[..])
+                .map_err(|_| TimelockError::InvalidChange)?;
+            match change {
+                TreasuryChange::SetOracleDexAddress { oracle_dex_address } => {
+                    self._set_oracle_dex_address(oracle_dex_address)?
+                }
+                TreasuryChange::SetLiabilityHorizons { liability_horizons } => {
+                    self.liability_horizons = liability_horizons;
+                    self.liability_reports = Vec::new();
+                }
+                TreasuryChange::SetPaymentRoute { token, path } => match path.is_empty() {
+                    true => self.payment_routes.remove(&token),
+                    false => self.payment_routes.insert(&token, &path),
+                },
+                TreasuryChange::SetMaxPriceAge { max_price_age } => {
+                    self.max_price_age = max_price_age
+                }
+                TreasuryChange::SetSwapToleranceBps { swap_tolerance_bps } => {
+                    self.swap_tolerance_bps = swap_tolerance_bps
+                }
+                TreasuryChange::SetMinDelay { min_delay } => self._set_min_delay(min_delay),
+            }
+            self._emit_change_executed(id);
+            Ok(())
+        }
+
+        fn schedule_change(&mut self, change: TreasuryChange) -> u32 {
+            self._schedule_change(scale::Encode::encode(&change))
+        }
 
-            let total_2D_treasury_liability =
-                liability_in_treasury_2D + liability_in_usdt_2D_treasury;
-            let mut total_2D_treasury_liability_state_health = 2;
+        ///Sum what becomes due within every LiabilityHorizon, compare it with the treasury balance,
+        ///emit a threshold event per breached horizon and store one LiabilityReport per horizon
+        #[ink(message)]
+        #[modifiers(only_any_role(&[ADMIN, MANAGER, KEEPER]))]
+        pub fn calculate_liabilities(&mut self) -> Result<(), TreasuryManagerError> {
+            self._calculate_liabilities()
+        }
+
+        fn _calculate_liabilities(&mut self) -> Result<(), TreasuryManagerError> {
+            let treasury_tokens_balance = self.treasury_balance();
+            let current_timestamp = self.env().block_timestamp();
+            // let current_timestamp = self.fake_timestamp;
 
             ink_env::debug_println!(
-                "calculate_liabilities threshold_1: {} threshold_2: {} total_2D_treasury_liability: {} total_2D_treasury_liability_state_health: {} liability_in_treasury_2D {} liability_in_usdt_2D_treasury {}",
-                threshold_1,threshold_2,total_2D_treasury_liability,total_2D_treasury_liability_state_health,liability_in_treasury_2D,liability_in_usdt_2D_treasury
+                "calculate_liabilities treasury_tokens_balance: {} current_timestamp: {}",
+                treasury_tokens_balance,
+                current_timestamp
             );
 
-            if total_2D_treasury_liability > threshold_1 {
-                let top_up_target = (total_2D_treasury_liability
-                    * (self.liabilities_thresholds[2 as usize]) as u128)
-                    / 100;
-                let mut top_up_amount = 0;
-                if top_up_target >= treasury_tokens_balance {
-                    top_up_amount = top_up_target - treasury_tokens_balance
+            let usdt_address = self.usdt_address()?;
+            let treasury_decimals = self.token_decimals(self.treasury_token_address);
+            //each foreign token is priced once through its own pair, with its decimals
+            let mut token_prices: Vec<(AccountId, Price, u8)> = Vec::new();
+
+            let horizons = self.liability_horizons.clone();
+            let mut reports: Vec<LiabilityReport> = horizons
+                .iter()
+                .map(|horizon| LiabilityReport {
+                    name: horizon.name.clone(),
+                    duration: horizon.duration,
+                    ..Default::default()
+                })
+                .collect();
+
+            let mut outstanding_jobs_ids = self.open_jobs_ids.clone();
+            outstanding_jobs_ids.extend(self.pending_jobs_ids.iter());
+            outstanding_jobs_ids.extend(self.partially_paid_jobs_ids.iter());
+            outstanding_jobs_ids.extend(self.failed_jobs_ids.iter());
+            outstanding_jobs_ids.extend(self.paused_jobs_ids.iter());
+            outstanding_jobs_ids.extend(self.cancelled_jobs_ids.iter());
+
+            for job_id in outstanding_jobs_ids {
+                let current_job: JobInfo = self
+                    .jobs
+                    .get(&job_id)
+                    .ok_or(TreasuryManagerError::UnknownJob)?;
+
+                //queued, failed and parked dues are owed whatever the horizon.
+                //A cancelled job owes nothing else
+                let outstanding_dues: Balance = current_job.payee_amounts_due.iter().sum();
+                let cancelled = current_job.status == JobStatus::Cancelled;
+                if cancelled && outstanding_dues == 0 {
+                    continue;
                 }
 
-                ink_env::debug_println!(
-                    "liability_in_treasury_2D: {} is above liabilities_thresholds LEVEL 1: {} treasury_tokens_balance: {} top_up_target: {} TOP UP NOW WITH  top_up_amount: {}",
-                    total_2D_treasury_liability,
-                    threshold_1, treasury_tokens_balance, top_up_target,  top_up_amount
-                );
-                total_2D_treasury_liability_state_health = 0;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_top {
-                    level_type: 2,
-                    current_balance: treasury_tokens_balance,
-                    top_up_amount: top_up_amount,
-                });
-            } else if total_2D_treasury_liability > threshold_2 {
-                ink_env::debug_println!(
-                    "liability_in_treasury_2D: {} is above liabilities_thresholds LEVEL 2: {} CONSIDER TOPPING UP",
-                    total_2D_treasury_liability,
-                    threshold_2
-                );
-                total_2D_treasury_liability_state_health = 1;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_med {
-                    level_type: 2,
-                    current_balance: treasury_tokens_balance,
-                    liability: total_2D_treasury_liability,
-                });
-            } else {
-                ink_env::debug_println!(
-                    "liability_in_treasury_2D: {} ALL GOOD is above liabilities_thresholds LEVEL 2: {}",
-                    total_2D_treasury_liability,
-                    threshold_2
-                );
-                total_2D_treasury_liability_state_health = 2;
-            }
-
-            let total_7D_treasury_liability =
-                liability_in_treasury_7D + liability_in_usdt_7D_treasury;
-            let mut total_7D_treasury_liability_state_health = 2;
-            if total_7D_treasury_liability > threshold_1 {
-                let top_up_target = (total_7D_treasury_liability
-                    * (self.liabilities_thresholds[2 as usize]) as u128)
-                    / 100;
-                let mut top_up_amount = 0;
-                if top_up_target >= treasury_tokens_balance {
-                    top_up_amount = top_up_target - treasury_tokens_balance
-                }
-
-                ink_env::debug_println!(
-                    "total_7D_treasury_liability: {} is above liabilities_thresholds LEVEL 1: {} treasury_tokens_balance: {} top_up_target: {} TOP UP NOW WITH  top_up_amount: {}",
-                    total_7D_treasury_liability,
-                    threshold_1, treasury_tokens_balance, top_up_target,  top_up_amount
-                );
-                total_7D_treasury_liability_state_health = 0;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_top {
-                    level_type: 7,
-                    current_balance: treasury_tokens_balance,
-                    top_up_amount: top_up_amount,
-                });
-            } else if total_7D_treasury_liability > threshold_2 {
-                ink_env::debug_println!(
-                    "total_7D_treasury_liability: {} is above liabilities_thresholds LEVEL 2: {} CONSIDER TOPPING UP",
-                    total_7D_treasury_liability,
-                    threshold_2
-                );
-                total_7D_treasury_liability_state_health = 1;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_med {
-                    level_type: 7,
-                    current_balance: treasury_tokens_balance,
-                    liability: total_7D_treasury_liability,
-                });
-            } else {
-                ink_env::debug_println!(
-                    "total_7D_treasury_liability: {} ALL GOOD is above liabilities_thresholds LEVEL 2: {}",
-                    total_7D_treasury_liability,
-                    threshold_2
-                );
-                total_7D_treasury_liability_state_health = 2;
-            }
-
-            let total_30D_treasury_liability =
-                liability_in_treasury_30D + liability_in_usdt_30D_treasury;
-            let mut total_30D_treasury_liability_state_health = 2;
-            if total_30D_treasury_liability > threshold_1 {
-                let top_up_target = (total_30D_treasury_liability
-                    * (self.liabilities_thresholds[2 as usize]) as u128)
-                    / 100;
-                let mut top_up_amount = 0;
-                if top_up_target >= treasury_tokens_balance {
-                    top_up_amount = top_up_target - treasury_tokens_balance
-                }
+                //USD valued treasury token jobs are accounted as USDT
+                let liability_token = if current_job.requested_token != self.treasury_token_address
+                {
+                    Some(current_job.requested_token)
+                } else if current_job.value_in_usd {
+                    Some(usdt_address)
+                } else {
+                    None
+                };
+                let (price, token_decimals) = match liability_token {
+                    Some(token) => {
+                        match token_prices.iter().find(|(priced, _, _)| *priced == token) {
+                            Some((_, price, decimals)) => (*price, *decimals),
+                            None => {
+                                let price = self.token_price(token, false)?;
+                                let decimals = self.token_decimals(token);
+                                token_prices.push((token, price, decimals));
+                                (price, decimals)
+                            }
+                        }
+                    }
+                    None => (Price::default(), treasury_decimals),
+                };
 
-                ink_env::debug_println!(
-                    "total_30D_treasury_liability: {} is above liabilities_thresholds LEVEL 1: {} treasury_tokens_balance: {} top_up_target: {} TOP UP NOW WITH  top_up_amount: {}",
-                    total_30D_treasury_liability,
-                    threshold_1, treasury_tokens_balance, top_up_target,  top_up_amount
-                );
-                total_30D_treasury_liability_state_health = 0;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_top {
-                    level_type: 30,
-                    current_balance: treasury_tokens_balance,
-                    top_up_amount: top_up_amount,
-                });
-            } else if total_30D_treasury_liability > threshold_2 {
-                ink_env::debug_println!(
-                    "total_30D_treasury_liability: {} is above liabilities_thresholds LEVEL 2: {} CONSIDER TOPPING UP",
-                    total_30D_treasury_liability,
-                    threshold_2
-                );
-                total_30D_treasury_liability_state_health = 1;
-                //EMIT EVENT
-                self.env().emit_event(liability_threshold_breached_med {
-                    level_type: 30,
-                    current_balance: treasury_tokens_balance,
-                    liability: total_30D_treasury_liability,
-                });
-            } else {
-                ink_env::debug_println!(
-                    "total_30D_treasury_liability: {} ALL GOOD is above liabilities_thresholds LEVEL 2: {}",
-                    total_30D_treasury_liability,
-                    threshold_2
-                );
-                total_30D_treasury_liability_state_health = 2;
-            }
-
-            let total_treasury_liability =
-                liability_in_treasury + liability_in_usdt_tokens_treasury;
-            let mut total_treasury_liability_state_health = 2;
-            if total_treasury_liability > threshold_1 {
-                let top_up_target = (total_treasury_liability
-                    * (self.liabilities_thresholds[2 as usize]) as u128)
-                    / 100;
-                let mut top_up_amount = 0;
-                if top_up_target >= treasury_tokens_balance {
-                    top_up_amount = top_up_target - treasury_tokens_balance
+                for (horizon, report) in horizons.iter().zip(reports.iter_mut()) {
+                    let due = if cancelled {
+                        outstanding_dues
+                    } else {
+                        outstanding_dues
+                            + Self::liability_until(
+                                &current_job,
+                                current_timestamp.saturating_add(horizon.duration),
+                            )
+                    };
+                    match liability_token {
+                        None => report.liability_in_treasury += due,
+                        Some(token) => {
+                            //foreign values and their treasury tokens equivalent, rounded up to stay on the safe side
+                            let in_treasury = Self::in_treasury_tokens(
+                                due,
+                                &price,
+                                treasury_decimals,
+                                token_decimals,
+                                Rounding::Up,
+                            )?;
+                            match report
+                                .foreign_liabilities
+                                .iter_mut()
+                                .find(|liability| liability.token == token)
+                            {
+                                Some(liability) => {
+                                    liability.amount += due;
+                                    liability.in_treasury += in_treasury;
+                                }
+                                None => report.foreign_liabilities.push(TokenLiability {
+                                    token,
+                                    amount: due,
+                                    in_treasury,
+                                }),
+                            }
+                        }
+                    }
                 }
+            }
+
+            for (horizon, report) in horizons.iter().zip(reports.iter_mut()) {
+                report.total_treasury_liability = report.liability_in_treasury
+                    + report
+                        .foreign_liabilities
+                        .iter()
+                        .map(|liability| liability.in_treasury)
+                        .sum::<Balance>();
+                self.assess_liability(horizon, report, treasury_tokens_balance);
+            }
+            self.liability_reports = reports;
+
+            Ok(())
+        }
+
+        ///Set health and top_up_amount on the report and emit the matching threshold event
+        fn assess_liability(
+            &self,
+            horizon: &LiabilityHorizon,
+            report: &mut LiabilityReport,
+            treasury_tokens_balance: Balance,
+        ) {
+            let critical_threshold = (treasury_tokens_balance * horizon.critical as u128) / 100;
+            let warning_threshold = (treasury_tokens_balance * horizon.warning as u128) / 100;
+
+            if report.total_treasury_liability > critical_threshold {
+                let top_up_target =
+                    (report.total_treasury_liability * horizon.top_up_target as u128) / 100;
+                report.top_up_amount = top_up_target.saturating_sub(treasury_tokens_balance);
+                report.health = 0;
 
                 ink_env::debug_println!(
-                    "total_treasury_liability: {} is above liabilities_thresholds LEVEL 1: {} treasury_tokens_balance: {} top_up_target: {} TOP UP NOW WITH  top_up_amount: {}",
-                    total_treasury_liability,
-                    threshold_1, treasury_tokens_balance, top_up_target,  top_up_amount
+                    "liability {} for horizon {} is above critical threshold: {} TOP UP NOW WITH top_up_amount: {}",
+                    report.total_treasury_liability,
+                    horizon.duration,
+                    critical_threshold,
+                    report.top_up_amount
                 );
-                total_treasury_liability_state_health = 0;
                 //EMIT EVENT
                 self.env().emit_event(liability_threshold_breached_top {
-                    level_type: 0,
+                    horizon_duration: horizon.duration,
                     current_balance: treasury_tokens_balance,
-                    top_up_amount: top_up_amount,
+                    top_up_amount: report.top_up_amount,
                 });
-            } else if total_treasury_liability > threshold_2 {
+            } else if report.total_treasury_liability > warning_threshold {
+                report.health = 1;
+
                 ink_env::debug_println!(
-                    "total_treasury_liability: {} is above liabilities_thresholds LEVEL 2: {}  CONSIDER TOPPING UP",
-                    total_treasury_liability,
-                    threshold_2
+                    "liability {} for horizon {} is above warning threshold: {} CONSIDER TOPPING UP",
+                    report.total_treasury_liability,
+                    horizon.duration,
+                    warning_threshold
                 );
-                total_treasury_liability_state_health = 1;
                 //EMIT EVENT
                 self.env().emit_event(liability_threshold_breached_med {
-                    level_type: 0,
+                    horizon_duration: horizon.duration,
                     current_balance: treasury_tokens_balance,
-                    liability: total_treasury_liability,
+                    liability: report.total_treasury_liability,
                 });
             } else {
-                ink_env::debug_println!(
-                    "total_treasury_liability: {} ALL GOOD is above liabilities_thresholds LEVEL 2: {}",
-                    total_treasury_liability,
-                    threshold_2
-                );
-                total_treasury_liability_state_health = 2;
-            }
-
-            self.liability_in_treasury = vec![
-                liability_in_treasury,
-                liability_in_treasury_2D,
-                liability_in_treasury_7D,
-                liability_in_treasury_30D,
-            ];
-            self.liability_in_usdt_tokens = vec![
-                liability_in_usdt_tokens,
-                liability_in_usdt_2D,
-                liability_in_usdt_7D,
-                liability_in_usdt_30D,
-            ];
-            self.liability_in_usdt_tokens_treasury = vec![
-                liability_in_usdt_tokens_treasury,
-                liability_in_usdt_2D_treasury,
-                liability_in_usdt_7D_treasury,
-                liability_in_usdt_30D_treasury,
-            ];
-            self.liability_health = vec![
-                total_treasury_liability_state_health,
-                total_2D_treasury_liability_state_health,
-                total_7D_treasury_liability_state_health,
-                total_30D_treasury_liability_state_health,
-            ];
-
-            Ok(())
+                report.health = 2;
+            }
         }
 
         //For MANAGER ONLY
@@ -1199,43 +2834,75 @@ pub mod treasury_manager {
             token_address: AccountId,
             to: AccountId,
             amount: Balance,
-        ) -> Result<(), AccessControlError> {
-            PSP22Ref::transfer(&token_address, to, amount, Vec::<u8>::new())
-                .expect("Transfer to external account did not go well");
+        ) -> Result<(), TreasuryManagerError> {
+            if PSP22Ref::balance_of(&token_address, self.env().account_id()) < amount {
+                return Err(TreasuryManagerError::InsufficientTreasuryBalance);
+            }
+
+            PSP22Ref::transfer_builder(&token_address, to, amount, Vec::<u8>::new())
+                .fire()
+                .map_err(|_| TreasuryManagerError::TransferFailed)??;
 
             //SHOULD EMMIT EVENT
 
             Ok(())
         }
 
-        #[ink(message)]
-        #[modifiers(only_role(MANAGER))]
-        pub fn register_foreign_asset(
+        fn _register_foreign_asset(
             &mut self,
             token_symbol: String,
             token_address: AccountId,
-        ) -> Result<(), AccessControlError> {
+        ) -> Result<(), TreasuryManagerError> {
             match self.foreign_assets.get(&token_symbol) {
                 Some(_) => (),
                 None => {
                     self.foreign_assets.insert(&token_symbol, &token_address);
                     self.foreign_assets_vec.push(token_symbol);
+                    //jobs can now request it
+                    if !self.non_native_tokens_vec.contains(&token_address) {
+                        self.non_native_tokens_vec.push(token_address);
+                    }
                 }
             }
 
             Ok(())
         }
 
+        #[ink(message)]
+        pub fn get_payment_route(&self, token: AccountId) -> Vec<AccountId> {
+            self.payment_route(token)
+        }
+
+        ///Pay token through intermediate pairs e.g. DOT -> USDT -> token when it is not listed against DOT.
+        ///path runs from the treasury token to token, an empty path goes back to the direct pair.
+        ///Scheduled on the timelock, execute_change applies it once the delay has passed
+        #[ink(message)]
+        #[modifiers(only_role(MANAGER))]
+        pub fn set_payment_route(
+            &mut self,
+            token: AccountId,
+            path: Vec<AccountId>,
+        ) -> Result<(), TreasuryManagerError> {
+            if !path.is_empty()
+                && (path.len() < 2
+                    || path.len() > MAX_PATH_LENGTH as usize
+                    || path[0] != self.treasury_token_address
+                    || path[path.len() - 1] != token)
+            {
+                return Err(TreasuryManagerError::InvalidPaymentRoute);
+            }
+            self.schedule_change(TreasuryChange::SetPaymentRoute { token, path });
+            Ok(())
+        }
+
         // ***        ***
         // *** ORACLE ***
 
         ///Set the Oracle_DEX Address that will be used
-        #[ink(message)]
-        #[modifiers(only_role(MANAGER))]
-        pub fn set_oracle_dex_address(
+        fn _set_oracle_dex_address(
             &mut self,
             oracle_dex_address: AccountId,
-        ) -> Result<(), AccessControlError> {
+        ) -> Result<(), TreasuryManagerError> {
             self.oracle_dex_address = oracle_dex_address;
             Ok(())
         }
@@ -1247,64 +2914,1116 @@ pub mod treasury_manager {
             // contract_address: AccountId,
             base_token: AccountId,
             quote_token: AccountId,
-        ) -> Balance {
+        ) -> Price {
             // OracleDexRef::get_pair_price(&contract_address, base_token, quote_token)
             OracleDexRef::get_pair_price(&self.oracle_dex_address, base_token, quote_token)
         }
 
-        ///Get Average Pair Price from Oracle
+        ///Get Average Pair Price from Oracle, over window_ms or the oracle's twap window if None
         #[ink(message)]
         pub fn get_average_price_for_pair(
             &self,
             // contract_address: AccountId,
             base_token: AccountId,
             quote_token: AccountId,
-        ) -> Balance {
-            OracleDexRef::get_average_price(&self.oracle_dex_address, base_token, quote_token)
+            window_ms: Option<u64>,
+        ) -> Price {
+            match window_ms {
+                Some(window) => OracleDexRef::get_twap(
+                    &self.oracle_dex_address,
+                    base_token,
+                    quote_token,
+                    window,
+                ),
+                None => OracleDexRef::get_average_price(
+                    &self.oracle_dex_address,
+                    base_token,
+                    quote_token,
+                ),
+            }
         }
 
+        ///Swap amount of path[0] along path on the oracle dex and pull what it returns into the treasury.
+        ///Fails unless at least min_amount_out of the last token of path arrived before deadline (ms)
         #[ink(message)]
+        #[modifiers(when_not_paused, only_role(TREASURER))]
         pub fn execute_swap(
             &mut self,
-            // contract_address: AccountId,
-            deposited_token: AccountId,
-            withdrawn_token: AccountId,
+            path: Vec<AccountId>,
             amount: Balance,
-            use_average_price: bool,
-        ) {
+            min_amount_out: Balance,
+            deadline: u64,
+        ) -> Result<Balance, TreasuryManagerError> {
+            self._execute_swap(path, amount, min_amount_out, deadline)
+        }
+
+        fn _execute_swap(
+            &mut self,
+            path: Vec<AccountId>,
+            amount: Balance,
+            min_amount_out: Balance,
+            deadline: u64,
+        ) -> Result<Balance, TreasuryManagerError> {
             let contract_address = self.oracle_dex_address;
+            let treasury = self.env().account_id();
+            let (deposited_token, withdrawn_token) = match (path.first(), path.last()) {
+                (Some(first), Some(last)) => (*first, *last),
+                _ => return Err(TreasuryManagerError::InvalidPaymentRoute),
+            };
 
-            PSP22Ref::approve(&deposited_token, contract_address, amount)
-                .expect("Approval for deposited_token did not go as planned");
+            PSP22Ref::approve(&deposited_token, contract_address, amount)?;
 
-            let withdrawn_amount = OracleDexRef::swap(
+            let w_amount = OracleDexRef::swap_exact_in(
                 &contract_address,
-                deposited_token,
-                withdrawn_token,
+                path,
                 amount,
-                self.env().caller().clone(),
-                use_average_price,
+                min_amount_out,
+                deadline,
+            )
+            .map_err(|error| match error {
+                OracleDexError::SlippageExceeded => TreasuryManagerError::SlippageExceeded,
+                OracleDexError::DeadlineExpired => TreasuryManagerError::SwapDeadlineExpired,
+                OracleDexError::InvalidPath => TreasuryManagerError::InvalidPaymentRoute,
+                _ => TreasuryManagerError::SwapFailed,
+            })?;
+
+            let balance_before = PSP22Ref::balance_of(&withdrawn_token, treasury);
+            PSP22Ref::transfer_from_builder(
+                &withdrawn_token,
+                contract_address,
+                treasury,
+                w_amount,
+                Vec::<u8>::new(),
+            )
+            .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
+            .fire()
+            .map_err(|_| TreasuryManagerError::TransferFailed)??;
+
+            //only what actually arrived counts
+            let delivered =
+                PSP22Ref::balance_of(&withdrawn_token, treasury).saturating_sub(balance_before);
+            if delivered == 0 {
+                return Err(TreasuryManagerError::SwapFailed);
+            }
+            if delivered < min_amount_out {
+                return Err(TreasuryManagerError::SlippageExceeded);
+            }
+
+            Ok(delivered)
+        }
+        // *** ORACLE ***
+        // ***        ***
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_env::{test, DefaultEnvironment};
+        use ink_lang as ink;
+
+        fn missing_role<T>() -> Result<T, TreasuryManagerError> {
+            Err(TreasuryManagerError::AccessControlError(
+                AccessControlError::MissingRole,
+            ))
+        }
+
+        ///bob administers and charlie manages, the token and oracle addresses are never called
+        fn new_treasury_manager() -> (TreasuryManager, test::DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let treasury_manager = TreasuryManager::new(
+                accounts.bob,
+                accounts.charlie,
+                String::from("DOT"),
+                AccountId::from([0x10; 32]),
+                AccountId::from([0x11; 32]),
+                AccountId::from([0x12; 32]),
+                10,
             );
+            (treasury_manager, accounts)
+        }
 
-            match withdrawn_amount {
-                Ok(w_amount) => {
-                    if w_amount > 0 {
-                        PSP22Ref::transfer_from_builder(
-                            &withdrawn_token,
-                            contract_address,
-                            self.env().account_id().clone(),
-                            w_amount,
-                            Vec::<u8>::new(),
-                        )
-                        .call_flags(ink_env::CallFlags::default().set_allow_reentry(true))
-                        .fire()
-                        .unwrap();
+        fn now() -> u64 {
+            ink_env::block_timestamp::<DefaultEnvironment>()
+        }
+
+        ///Advance blocks until the block timestamp is past timestamp
+        fn advance_past(timestamp: u64) {
+            while now() <= timestamp {
+                test::advance_block::<DefaultEnvironment>();
+            }
+        }
+
+        ///Every id sits in the vector of its job's status at its position_in_vec
+        fn assert_positions_match(treasury_manager: &TreasuryManager) {
+            for status in [
+                JobStatus::Open,
+                JobStatus::Pending,
+                JobStatus::PartiallyPaid,
+                JobStatus::Completed,
+                JobStatus::Cancelled,
+                JobStatus::Failed,
+                JobStatus::Paused,
+            ] {
+                for (position, id) in treasury_manager.jobs_vec(status).iter().enumerate() {
+                    let job = treasury_manager.get_job_info(*id).unwrap();
+                    assert_eq!(job.status, status);
+                    assert_eq!(job.position_in_vec, position as u32);
+                }
+            }
+        }
+
+        ///bob adds a job paid in the treasury token and gets back its id
+        fn add_job(
+            treasury_manager: &mut TreasuryManager,
+            requested_value: Balance,
+            payment_type: PaymentType,
+            payment_schedule: Vec<u64>,
+            payment_plan: PaymentPlan,
+            payee_accounts: Vec<AccountId>,
+            payee_shares: Vec<u16>,
+        ) -> Result<u32, TreasuryManagerError> {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            treasury_manager.add_job(
+                String::from("job"),
+                String::from("hash"),
+                accounts.eve,
+                AccountId::from([0x10; 32]),
+                false,
+                requested_value,
+                payment_type,
+                payment_schedule,
+                payment_plan,
+                payee_accounts,
+                payee_shares,
+            )?;
+            Ok(treasury_manager.next_id - 1)
+        }
+
+        #[ink::test]
+        fn sweeps_reject_callers_without_keeper_role() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+
+            assert_eq!(treasury_manager.check_open_jobs(10), missing_role());
+            assert_eq!(treasury_manager.check_pending_jobs(10), missing_role());
+            assert_eq!(treasury_manager.make_native_payments(10), missing_role());
+            assert_eq!(
+                treasury_manager.make_native_usd_payments(10),
+                missing_role()
+            );
+            assert_eq!(
+                treasury_manager.make_non_native_payments(10),
+                missing_role()
+            );
+            assert_eq!(treasury_manager.calculate_liabilities(), missing_role());
+        }
+
+        #[ink::test]
+        fn sweeps_accept_keeper_until_removed() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(treasury_manager.add_keeper(accounts.django), missing_role());
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(treasury_manager.add_keeper(accounts.django), Ok(()));
+
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+            assert!(treasury_manager.make_native_payments(10).is_ok());
+            assert!(treasury_manager.make_non_native_payments(10).is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(treasury_manager.remove_keeper(accounts.django), Ok(()));
+
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert_eq!(treasury_manager.check_open_jobs(10), missing_role());
+            assert_eq!(treasury_manager.make_native_payments(10), missing_role());
+        }
+
+        #[ink::test]
+        fn execute_swap_rejects_callers_without_treasurer_role() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+
+            //neither the manager nor a keeper can swap treasury tokens
+            for caller in [accounts.charlie, accounts.eve] {
+                test::set_caller::<DefaultEnvironment>(caller);
+                assert_eq!(
+                    treasury_manager.execute_swap(Vec::new(), 100, 0, u64::MAX),
+                    missing_role()
+                );
+            }
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(treasury_manager.add_keeper(accounts.eve), Ok(()));
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                treasury_manager.execute_swap(Vec::new(), 100, 0, u64::MAX),
+                missing_role()
+            );
+        }
+
+        #[ink::test]
+        fn execute_swap_accepts_treasurer_until_removed() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(treasury_manager.add_treasurer(accounts.frank), Ok(()));
+
+            //an empty path fails on the route, past the role check and before any cross contract call
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                treasury_manager.execute_swap(Vec::new(), 100, 0, u64::MAX),
+                Err(TreasuryManagerError::InvalidPaymentRoute)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(treasury_manager.remove_treasurer(accounts.frank), Ok(()));
+
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                treasury_manager.execute_swap(Vec::new(), 100, 0, u64::MAX),
+                missing_role()
+            );
+        }
+
+        #[ink::test]
+        fn approvals_of_removed_approvers_do_not_count() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let set_approvers = |approvers: Vec<AccountId>| Operation::SetApprovers {
+                approvers,
+                threshold: 2,
+            };
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(treasury_manager
+                .propose_operation(set_approvers(vec![
+                    accounts.bob,
+                    accounts.eve,
+                    accounts.frank
+                ]))
+                .is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            let add_guardian = treasury_manager
+                .propose_operation(Operation::AddGuardian {
+                    account: accounts.django,
+                })
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let remove_eve = treasury_manager
+                .propose_operation(set_approvers(vec![accounts.bob, accounts.frank]))
+                .unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(treasury_manager.approve_operation(remove_eve), Ok(()));
+
+            //eve's approval no longer counts, bob's alone is below the threshold
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(treasury_manager.approve_operation(add_guardian), Ok(()));
+            assert!(!treasury_manager.has_role(GUARDIAN, accounts.django));
+            assert_eq!(
+                treasury_manager
+                    .get_proposal(add_guardian)
+                    .unwrap()
+                    .approvals,
+                vec![accounts.bob]
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(treasury_manager.approve_operation(add_guardian), Ok(()));
+            assert!(treasury_manager.has_role(GUARDIAN, accounts.django));
+        }
+
+        ///The pallet launches its treasury manager through the factory with its own account as
+        ///contract_administrator, so the pallet holds the approver seat and the factory none
+        #[ink::test]
+        fn approver_seat_follows_contract_administrator_from_pallet_launch() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let factory = AccountId::from([0x20; 32]);
+            let pallet = AccountId::from([0x21; 32]);
+            test::set_caller::<DefaultEnvironment>(factory);
+            let mut treasury_manager = TreasuryManager::new(
+                pallet,
+                accounts.charlie,
+                String::from("DOT"),
+                AccountId::from([0x10; 32]),
+                AccountId::from([0x11; 32]),
+                AccountId::from([0x12; 32]),
+                10,
+            );
+            assert_eq!(treasury_manager.get_approvers(), (vec![pallet], 1));
+
+            assert_eq!(
+                treasury_manager.propose_operation(Operation::ProposeManager {
+                    account: accounts.django,
+                }),
+                Err(TreasuryManagerError::NotApprover)
+            );
+
+            //what propose_treasury_contract_manager and propose_treasury_contract_admin send
+            test::set_caller::<DefaultEnvironment>(pallet);
+            assert!(treasury_manager
+                .propose_operation(Operation::ProposeManager {
+                    account: accounts.django,
+                })
+                .is_ok());
+            assert_eq!(
+                treasury_manager
+                    .get_pending_manager()
+                    .map(|pending| pending.account),
+                Some(accounts.django)
+            );
+            assert_eq!(
+                treasury_manager.propose_operation(Operation::SetApprovers {
+                    approvers: vec![accounts.eve],
+                    threshold: 1,
+                }),
+                Err(TreasuryManagerError::InvalidApprovers)
+            );
+            assert!(treasury_manager
+                .propose_operation(Operation::ProposeAdmin {
+                    account: accounts.eve,
+                })
+                .is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(treasury_manager.accept_admin(), Ok(()));
+            assert_eq!(treasury_manager.get_approvers(), (vec![accounts.eve], 1));
+            assert!(treasury_manager
+                .propose_operation(Operation::ProposeManager {
+                    account: accounts.frank,
+                })
+                .is_ok());
+
+            test::set_caller::<DefaultEnvironment>(pallet);
+            assert_eq!(
+                treasury_manager.propose_operation(Operation::ProposeManager {
+                    account: accounts.django,
+                }),
+                Err(TreasuryManagerError::NotApprover)
+            );
+        }
+
+        #[ink::test]
+        fn previous_admin_cannot_propose_or_approve_after_handover() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(treasury_manager
+                .propose_operation(Operation::SetApprovers {
+                    approvers: vec![accounts.bob, accounts.frank],
+                    threshold: 2,
+                })
+                .is_ok());
+
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            let propose_manager = treasury_manager
+                .propose_operation(Operation::ProposeManager {
+                    account: accounts.django,
+                })
+                .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            let propose_admin = treasury_manager
+                .propose_operation(Operation::ProposeAdmin {
+                    account: accounts.eve,
+                })
+                .unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(treasury_manager.approve_operation(propose_admin), Ok(()));
+
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(treasury_manager.accept_admin(), Ok(()));
+            assert_eq!(
+                treasury_manager.get_approvers(),
+                (vec![accounts.eve, accounts.frank], 2)
+            );
+            assert!(!treasury_manager.has_role(ADMIN, accounts.bob));
+            assert!(!treasury_manager.has_role(GUARDIAN, accounts.bob));
+            assert!(treasury_manager.has_role(GUARDIAN, accounts.eve));
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                treasury_manager.approve_operation(propose_manager),
+                Err(TreasuryManagerError::NotApprover)
+            );
+            assert_eq!(
+                treasury_manager.propose_operation(Operation::AdminWithdrawal {
+                    amount: 100,
+                    to: accounts.bob,
+                }),
+                Err(TreasuryManagerError::NotApprover)
+            );
+            assert_eq!(
+                treasury_manager.pause(String::from("old key")),
+                missing_role()
+            );
+
+            //the new admin's seat carries the pending proposal over the threshold
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            assert_eq!(treasury_manager.approve_operation(propose_manager), Ok(()));
+            assert_eq!(
+                treasury_manager
+                    .get_pending_manager()
+                    .map(|pending| pending.account),
+                Some(accounts.django)
+            );
+        }
+
+        #[ink::test]
+        fn payee_shares_split_with_the_remainder_to_the_last_payee() {
+            assert_eq!(
+                TreasuryManager::split_by_shares(1001, &vec![3333, 3333, 3334]),
+                vec![333, 333, 335]
+            );
+            assert_eq!(
+                TreasuryManager::split_by_shares(7, &vec![PAYEE_SHARES_TOTAL]),
+                vec![7]
+            );
+            assert_eq!(
+                TreasuryManager::split_by_shares(1, &vec![5000, 5000]),
+                vec![0, 1]
+            );
+        }
+
+        #[ink::test]
+        fn payee_shares_must_cover_every_payee_and_add_up() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let payees = vec![accounts.django, accounts.frank];
+
+            assert_eq!(
+                TreasuryManager::resolve_payee_shares(&vec![accounts.django], Vec::new()),
+                Ok(vec![PAYEE_SHARES_TOTAL])
+            );
+            for shares in [
+                Vec::new(),
+                vec![PAYEE_SHARES_TOTAL],
+                vec![5000, 4999],
+                vec![0, PAYEE_SHARES_TOTAL],
+            ] {
+                assert_eq!(
+                    TreasuryManager::resolve_payee_shares(&payees, shares),
+                    Err(TreasuryManagerError::InvalidPayeeShares)
+                );
+            }
+            assert_eq!(
+                TreasuryManager::resolve_payee_shares(&payees, vec![2500, 7500]),
+                Ok(vec![2500, 7500])
+            );
+        }
+
+        #[ink::test]
+        fn due_payment_is_split_between_payees() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let due = now() + 10;
+            let id = add_job(
+                &mut treasury_manager,
+                999,
+                PaymentType::OneOffFutureTime,
+                vec![due],
+                PaymentPlan::default(),
+                vec![accounts.django, accounts.frank],
+                vec![2500, 7500],
+            )
+            .unwrap();
+
+            advance_past(due);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(job.status, JobStatus::Pending);
+            assert_eq!(job.payee_amounts_due, vec![249, 750]);
+            assert_eq!(treasury_manager.get_native_payments_ids(), vec![id]);
+        }
+
+        #[ink::test]
+        fn payment_plan_splits_evenly_or_checks_the_given_amounts() {
+            let schedule = vec![10, 20, 30];
+            let plan = |instalment_amounts: Vec<Balance>, cliff: Option<u64>| PaymentPlan {
+                instalment_amounts,
+                cliff,
+            };
+            let resolve = |schedule: &Vec<u64>, plan: &PaymentPlan| {
+                TreasuryManager::resolve_payment_plan(
+                    &PaymentType::Instalments,
+                    1000,
+                    schedule,
+                    plan,
+                )
+            };
+
+            assert_eq!(
+                resolve(&schedule, &PaymentPlan::default()),
+                Ok(vec![333, 333, 334])
+            );
+            assert_eq!(
+                resolve(&schedule, &plan(vec![100, 0, 900], Some(30))),
+                Ok(vec![100, 0, 900])
+            );
+            assert_eq!(
+                resolve(&schedule, &plan(vec![100, 900], None)),
+                Err(TreasuryManagerError::InstalmentAmountsMismatch)
+            );
+            assert_eq!(
+                resolve(&schedule, &plan(vec![100, 100, 900], None)),
+                Err(TreasuryManagerError::InstalmentAmountsMismatch)
+            );
+            assert_eq!(
+                resolve(&schedule, &plan(Vec::new(), Some(31))),
+                Err(TreasuryManagerError::InvalidCliff)
+            );
+            assert_eq!(
+                resolve(&vec![10, 10, 30], &PaymentPlan::default()),
+                Err(TreasuryManagerError::ScheduleNotIncreasing)
+            );
+            assert_eq!(
+                resolve(&Vec::new(), &PaymentPlan::default()),
+                Err(TreasuryManagerError::EmptySchedule)
+            );
+        }
+
+        #[ink::test]
+        fn instalments_before_the_cliff_become_due_together_once_it_passes() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now();
+            let cliff = start + 4_000;
+            let id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::Instalments,
+                vec![start + 1_000, start + 2_000, start + 3_000, start + 6_000],
+                PaymentPlan {
+                    instalment_amounts: vec![100, 200, 300, 400],
+                    cliff: Some(cliff),
+                },
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(TreasuryManager::instalment_due_timestamp(&job, 0), cliff);
+            assert_eq!(TreasuryManager::instalment_due_timestamp(&job, 2), cliff);
+            assert_eq!(
+                TreasuryManager::instalment_due_timestamp(&job, 3),
+                start + 6_000
+            );
+
+            //the first instalment is due but held back by the cliff
+            advance_past(start + 3_000);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert_eq!(
+                treasury_manager.get_job_info(id).unwrap().status,
+                JobStatus::Open
+            );
+
+            advance_past(cliff);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(job.status, JobStatus::Pending);
+            assert_eq!(job.payee_amounts_due, vec![600]);
+            assert_eq!(job.next_installment_pointer, 3);
+            assert_eq!(TreasuryManager::liability_until(&job, u64::MAX), 400);
+
+            advance_past(start + 6_000);
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(job.payee_amounts_due, vec![1000]);
+            assert_eq!(job.next_installment_pointer, 4);
+            assert_eq!(TreasuryManager::liability_until(&job, u64::MAX), 0);
+        }
+
+        #[ink::test]
+        fn stream_releases_requested_value_linearly_rounding_down() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now() + 1_000;
+            let id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::Streaming {
+                    start,
+                    end: start + 3_000,
+                },
+                Vec::new(),
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+            let mut job = treasury_manager.get_job_info(id).unwrap();
+
+            for (timestamp, streamed) in [
+                (0, 0),
+                (start, 0),
+                (start + 1, 0),
+                (start + 3, 1),
+                (start + 1_500, 500),
+                (start + 2_999, 999),
+                (start + 3_000, 1000),
+                (u64::MAX, 1000),
+            ] {
+                assert_eq!(TreasuryManager::streamed_until(&job, timestamp), streamed);
+            }
+
+            //only what was not claimed yet is still a liability
+            job.streamed_amount = 400;
+            assert_eq!(TreasuryManager::liability_until(&job, start + 1_500), 100);
+            assert_eq!(TreasuryManager::liability_until(&job, start + 300), 0);
+            assert_eq!(TreasuryManager::liability_until(&job, u64::MAX), 600);
+        }
+
+        #[ink::test]
+        fn stream_window_replaces_the_schedule() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let stream = |start: u64, end: u64| PaymentType::Streaming { start, end };
+            let mut add_stream = |payment_type: PaymentType,
+                                  payment_schedule: Vec<u64>,
+                                  payment_plan: PaymentPlan| {
+                add_job(
+                    &mut treasury_manager,
+                    1000,
+                    payment_type,
+                    payment_schedule,
+                    payment_plan,
+                    vec![accounts.django],
+                    Vec::new(),
+                )
+            };
+
+            assert_eq!(
+                add_stream(stream(100, 100), Vec::new(), PaymentPlan::default()),
+                Err(TreasuryManagerError::InvalidStreamWindow)
+            );
+            assert_eq!(
+                add_stream(stream(200, 100), Vec::new(), PaymentPlan::default()),
+                Err(TreasuryManagerError::InvalidStreamWindow)
+            );
+            assert_eq!(
+                add_stream(stream(100, 200), vec![150], PaymentPlan::default()),
+                Err(TreasuryManagerError::InvalidJob)
+            );
+            assert_eq!(
+                add_stream(
+                    stream(100, 200),
+                    Vec::new(),
+                    PaymentPlan {
+                        instalment_amounts: Vec::new(),
+                        cliff: Some(150),
                     }
+                ),
+                Err(TreasuryManagerError::InvalidJob)
+            );
+            assert!(add_stream(stream(100, 200), Vec::new(), PaymentPlan::default()).is_ok());
+        }
+
+        #[ink::test]
+        fn stream_is_only_claimed_by_its_payees_once_it_accrued() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now() + 1_000;
+            let stream_id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::Streaming {
+                    start,
+                    end: start + 3_000,
+                },
+                Vec::new(),
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+            let one_off_id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::OneOffFutureTime,
+                vec![start],
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.django);
+            assert_eq!(
+                treasury_manager.claim_stream(one_off_id),
+                Err(TreasuryManagerError::NotStreamingJob)
+            );
+            assert_eq!(
+                treasury_manager.claim_stream(stream_id),
+                Err(TreasuryManagerError::NothingToClaim)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                treasury_manager.claim_stream(stream_id),
+                Err(TreasuryManagerError::NotPayee)
+            );
+
+            //the sweeps move a started stream on but leave its accrual to claim_stream
+            advance_past(start + 1_500);
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+            let job = treasury_manager.get_job_info(stream_id).unwrap();
+            assert_eq!(job.status, JobStatus::Pending);
+            assert_eq!(job.payee_amounts_due, vec![0]);
+            assert_eq!(job.streamed_amount, 0);
+        }
+
+        #[ink::test]
+        fn cancelling_a_stream_pays_its_accrual_and_claws_back_the_rest() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now();
+            let id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::Streaming {
+                    start,
+                    end: start + 3_000,
+                },
+                Vec::new(),
+                PaymentPlan::default(),
+                vec![accounts.django, accounts.frank],
+                vec![3333, 6667],
+            )
+            .unwrap();
+
+            advance_past(start + 1_000);
+            let accrued = 1000 * (now() - start) as u128 / 3_000;
+            assert_eq!(
+                treasury_manager.cancel_job(id, String::from("scope dropped")),
+                Ok(())
+            );
+
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(job.status, JobStatus::Cancelled);
+            assert_eq!(job.cancellation_reason, String::from("scope dropped"));
+            assert_eq!(job.streamed_amount, accrued);
+            assert_eq!(
+                job.payee_amounts_due,
+                TreasuryManager::split_by_shares(accrued, &vec![3333, 6667])
+            );
+            assert_eq!(
+                TreasuryManager::liability_until(&job, u64::MAX),
+                1000 - accrued
+            );
+            assert_eq!(treasury_manager.get_native_payments_ids(), vec![id]);
+            assert_eq!(treasury_manager.get_cancelled_jobs_ids(0, 10), vec![id]);
+            assert_positions_match(&treasury_manager);
+
+            assert_eq!(
+                treasury_manager.cancel_job(id, String::from("again")),
+                Err(TreasuryManagerError::InvalidJobState)
+            );
+        }
+
+        #[ink::test]
+        fn cancelling_keeps_accrued_instalments_due_and_drops_the_rest() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now();
+            let accrued_id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::Instalments,
+                vec![start + 1_000, start + 5_000],
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+            let open_id = add_job(
+                &mut treasury_manager,
+                1000,
+                PaymentType::OneOffFutureTime,
+                vec![start + 5_000],
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+
+            advance_past(start + 1_000);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert!(treasury_manager.check_pending_jobs(10).is_ok());
+            assert_eq!(
+                treasury_manager
+                    .get_job_info(accrued_id)
+                    .unwrap()
+                    .payee_amounts_due,
+                vec![500]
+            );
+
+            for id in [accrued_id, open_id] {
+                assert_eq!(treasury_manager.cancel_job(id, String::new()), Ok(()));
+            }
+            let job = treasury_manager.get_job_info(accrued_id).unwrap();
+            assert_eq!(job.payee_amounts_due, vec![500]);
+            assert_eq!(TreasuryManager::liability_until(&job, u64::MAX), 500);
+            assert_eq!(treasury_manager.get_native_payments_ids(), vec![accrued_id]);
+            assert_eq!(
+                treasury_manager.get_cancelled_jobs_ids(0, 10),
+                vec![accrued_id, open_id]
+            );
+            assert!(treasury_manager.get_open_jobs_ids(0, 10).is_empty());
+            assert!(treasury_manager.get_pending_jobs_ids(0, 10).is_empty());
+            assert_positions_match(&treasury_manager);
+        }
+
+        #[ink::test]
+        fn amending_a_stream_rebases_it_and_is_refused_once_it_ended() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let start = now();
+            let end = start + 3_000;
+            let id = add_job(
+                &mut treasury_manager,
+                600,
+                PaymentType::Streaming { start, end },
+                Vec::new(),
+                PaymentPlan::default(),
+                vec![accounts.django],
+                Vec::new(),
+            )
+            .unwrap();
+
+            advance_past(start + 1_000);
+            let amended_at = now();
+            let accrued = 600 * (amended_at - start) as u128 / 3_000;
+
+            //dues owed to django cannot move to frank
+            assert_eq!(
+                treasury_manager.amend_job(
+                    id,
+                    900,
+                    Vec::new(),
+                    PaymentPlan::default(),
+                    vec![accounts.frank],
+                    Vec::new(),
+                ),
+                Err(TreasuryManagerError::InvalidJobState)
+            );
+            assert_eq!(
+                treasury_manager.amend_job(
+                    id,
+                    900,
+                    Vec::new(),
+                    PaymentPlan::default(),
+                    vec![accounts.django],
+                    Vec::new(),
+                ),
+                Ok(())
+            );
+
+            let job = treasury_manager.get_job_info(id).unwrap();
+            assert_eq!(job.requested_value, 900);
+            assert_eq!(
+                job.payment_type,
+                PaymentType::Streaming {
+                    start: amended_at,
+                    end,
                 }
-                _ => (),
+            );
+            assert_eq!(job.streamed_amount, 0);
+            assert_eq!(job.payee_amounts_due, vec![accrued]);
+            assert_eq!(TreasuryManager::streamed_until(&job, end), 900);
+            assert_eq!(treasury_manager.get_native_payments_ids(), vec![id]);
+
+            advance_past(end);
+            assert_eq!(
+                treasury_manager.amend_job(
+                    id,
+                    900,
+                    Vec::new(),
+                    PaymentPlan::default(),
+                    vec![accounts.django],
+                    Vec::new(),
+                ),
+                Err(TreasuryManagerError::InvalidJobState)
+            );
+        }
+
+        #[ink::test]
+        fn pausing_resuming_and_removing_jobs_keep_positions_consistent() {
+            let (mut treasury_manager, accounts) = new_treasury_manager();
+            let due = now() + 1_000;
+            let ids: Vec<u32> = (0..3)
+                .map(|_| {
+                    add_job(
+                        &mut treasury_manager,
+                        100,
+                        PaymentType::OneOffFutureTime,
+                        vec![due],
+                        PaymentPlan::default(),
+                        vec![accounts.django],
+                        Vec::new(),
+                    )
+                    .unwrap()
+                })
+                .collect();
+
+            assert_eq!(treasury_manager.pause_job(ids[0]), Ok(()));
+            assert_eq!(
+                treasury_manager.get_open_jobs_ids(0, 10),
+                vec![ids[2], ids[1]]
+            );
+            assert_eq!(treasury_manager.get_paused_jobs_ids(0, 10), vec![ids[0]]);
+            assert_positions_match(&treasury_manager);
+
+            assert_eq!(
+                treasury_manager.resume_job(ids[1]),
+                Err(TreasuryManagerError::InvalidJobState)
+            );
+            assert_eq!(treasury_manager.resume_job(ids[0]), Ok(()));
+            assert_eq!(
+                treasury_manager.get_open_jobs_ids(0, 10),
+                vec![ids[2], ids[1], ids[0]]
+            );
+            assert_positions_match(&treasury_manager);
+
+            assert_eq!(treasury_manager.remove_job_info(ids[2]), Ok(()));
+            assert_eq!(treasury_manager.get_job_info(ids[2]), None);
+            assert_eq!(
+                treasury_manager.get_open_jobs_ids(0, 10),
+                vec![ids[0], ids[1]]
+            );
+            assert_positions_match(&treasury_manager);
+
+            //the remaining jobs are still checked without a missing id in the way
+            advance_past(due);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            assert_eq!(
+                treasury_manager.get_pending_jobs_ids(0, 10),
+                vec![ids[0], ids[1]]
+            );
+            assert_positions_match(&treasury_manager);
+        }
+
+        ///bob adds count one off jobs of 100 due after due and gets back their ids
+        fn add_one_off_jobs(
+            treasury_manager: &mut TreasuryManager,
+            count: u32,
+            due: u64,
+        ) -> Vec<u32> {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            (0..count)
+                .map(|_| {
+                    add_job(
+                        treasury_manager,
+                        100,
+                        PaymentType::OneOffFutureTime,
+                        vec![due],
+                        PaymentPlan::default(),
+                        vec![accounts.django],
+                        Vec::new(),
+                    )
+                    .unwrap()
+                })
+                .collect()
+        }
+
+        #[ink::test]
+        fn open_sweep_carries_on_from_its_cursor_and_starts_over_at_the_end() {
+            let (mut treasury_manager, _) = new_treasury_manager();
+            add_one_off_jobs(&mut treasury_manager, 5, now() + 1_000);
+            let progress = |processed: u32, remaining: u32| BatchProgress {
+                processed,
+                completed: 0,
+                remaining,
+            };
+
+            assert_eq!(treasury_manager.check_open_jobs(2), Ok(progress(2, 3)));
+            assert_eq!(treasury_manager.get_sweep_cursors()[0], 2);
+            assert_eq!(treasury_manager.check_open_jobs(2), Ok(progress(2, 1)));
+            assert_eq!(treasury_manager.get_sweep_cursors()[0], 4);
+            assert_eq!(treasury_manager.check_open_jobs(2), Ok(progress(1, 0)));
+            assert_eq!(treasury_manager.get_sweep_cursors()[0], 0);
+            assert_eq!(treasury_manager.check_open_jobs(0), Ok(progress(0, 5)));
+        }
+
+        #[ink::test]
+        fn open_sweep_skips_nothing_when_a_checked_job_is_detached() {
+            let (mut treasury_manager, _) = new_treasury_manager();
+            let start = now();
+            let later = add_one_off_jobs(&mut treasury_manager, 3, start + 100_000);
+            let soon = add_one_off_jobs(&mut treasury_manager, 2, start + 1_000);
+
+            assert!(treasury_manager.check_open_jobs(3).is_ok());
+            assert_eq!(treasury_manager.get_sweep_cursors()[0], 3);
+
+            //the last checked job takes the paused one's place and the cursor moves back over the slot it left
+            assert_eq!(treasury_manager.pause_job(later[1]), Ok(()));
+            assert_eq!(treasury_manager.get_sweep_cursors()[0], 2);
+            assert_eq!(
+                treasury_manager.get_open_jobs_ids(0, 10),
+                vec![later[0], later[2], soon[1], soon[0]]
+            );
+            assert_positions_match(&treasury_manager);
+
+            advance_past(start + 1_000);
+            assert_eq!(
+                treasury_manager.check_open_jobs(10),
+                Ok(BatchProgress {
+                    processed: 2,
+                    completed: 2,
+                    remaining: 0,
+                })
+            );
+            assert_eq!(
+                treasury_manager.get_pending_jobs_ids(0, 10),
+                vec![soon[1], soon[0]]
+            );
+            assert_positions_match(&treasury_manager);
+        }
+
+        #[ink::test]
+        fn pending_sweep_skips_nothing_when_a_checked_job_is_detached() {
+            let (mut treasury_manager, _) = new_treasury_manager();
+            let due = now() + 1_000;
+            add_one_off_jobs(&mut treasury_manager, 4, due);
+            advance_past(due);
+            assert!(treasury_manager.check_open_jobs(10).is_ok());
+            let pending = treasury_manager.get_pending_jobs_ids(0, 10);
+            assert_eq!(pending.len(), 4);
+
+            assert_eq!(
+                treasury_manager.check_pending_jobs(2),
+                Ok(BatchProgress {
+                    processed: 2,
+                    completed: 2,
+                    remaining: 2,
+                })
+            );
+            assert_eq!(treasury_manager.get_sweep_cursors()[1], 2);
+
+            assert_eq!(
+                treasury_manager.cancel_job(pending[0], String::new()),
+                Ok(())
+            );
+            assert_eq!(treasury_manager.get_sweep_cursors()[1], 1);
+            assert_eq!(
+                treasury_manager.get_pending_jobs_ids(0, 10),
+                vec![pending[1], pending[3], pending[2]]
+            );
+            assert_positions_match(&treasury_manager);
+
+            assert_eq!(
+                treasury_manager.check_pending_jobs(10),
+                Ok(BatchProgress {
+                    processed: 2,
+                    completed: 2,
+                    remaining: 0,
+                })
+            );
+            for id in &pending {
+                assert_eq!(
+                    treasury_manager
+                        .get_job_info(*id)
+                        .unwrap()
+                        .payee_amounts_due,
+                    vec![100]
+                );
             }
+            assert_eq!(treasury_manager.get_sweep_cursors()[1], 0);
         }
-        // *** ORACLE ***
-        // ***        ***
     }
 }
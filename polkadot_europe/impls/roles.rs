@@ -0,0 +1,16 @@
+use openbrush::{contracts::access_control::*, modifier_definition};
+
+/// Same as only_role but lets the caller through if it holds any of roles
+#[modifier_definition]
+pub fn only_any_role<T, F, R, E>(instance: &mut T, body: F, roles: &[RoleType]) -> Result<R, E>
+where
+    T: AccessControl,
+    F: FnOnce(&mut T) -> Result<R, E>,
+    E: From<AccessControlError>,
+{
+    let caller = ink_env::caller::<ink_env::DefaultEnvironment>();
+    if !roles.iter().any(|role| instance.has_role(*role, caller)) {
+        return Err(From::from(AccessControlError::MissingRole));
+    }
+    body(instance)
+}
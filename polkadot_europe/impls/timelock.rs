@@ -0,0 +1,119 @@
+pub use crate::traits::timelock::*;
+
+use ink_prelude::vec::Vec;
+use openbrush::{
+    contracts::access_control::{self, *},
+    modifiers,
+    storage::Mapping,
+    traits::Storage,
+};
+
+/// Cancels scheduled changes and pauses contracts
+pub const GUARDIAN: RoleType = ink_lang::selector_id!("GUARDIAN");
+
+pub const STORAGE_KEY: u32 = openbrush::storage_unique_key!(Data);
+
+#[derive(Default, Debug)]
+#[openbrush::upgradeable_storage(STORAGE_KEY)]
+pub struct Data {
+    pub min_delay: u64,
+    pub next_change_id: u32,
+    pub changes: Mapping<u32, ScheduledChange>,
+    pub scheduled_ids: Vec<u32>,
+}
+
+impl<T: Storage<Data> + Storage<access_control::Data>> Timelock for T {
+    default fn get_min_delay(&self) -> u64 {
+        self.data::<Data>().min_delay
+    }
+
+    default fn get_scheduled_change_ids(&self) -> Vec<u32> {
+        self.data::<Data>().scheduled_ids.clone()
+    }
+
+    default fn get_scheduled_change(&self, id: u32) -> Option<ScheduledChange> {
+        self.data::<Data>().changes.get(&id)
+    }
+
+    #[modifiers(only_role(GUARDIAN))]
+    default fn cancel_change(&mut self, id: u32) -> Result<(), TimelockError> {
+        self._remove_change(id)?;
+        self._emit_change_cancelled(id);
+        Ok(())
+    }
+}
+
+pub trait Internal {
+    fn _emit_change_scheduled(&self, _id: u32, _executable_at: u64);
+
+    fn _emit_change_cancelled(&self, _id: u32);
+
+    fn _emit_change_executed(&self, _id: u32);
+
+    /// Schedule call to become executable min_delay from now. Returns the change id
+    fn _schedule_change(&mut self, call: Vec<u8>) -> u32;
+
+    /// Remove a change whose delay has passed and return its call for the contract to apply
+    fn _take_ready_change(&mut self, id: u32) -> Result<Vec<u8>, TimelockError>;
+
+    fn _remove_change(&mut self, id: u32) -> Result<ScheduledChange, TimelockError>;
+
+    /// Only from an executed change, so lowering the delay waits out the current one
+    fn _set_min_delay(&mut self, min_delay: u64);
+}
+
+impl<T: Storage<Data>> Internal for T {
+    default fn _emit_change_scheduled(&self, _id: u32, _executable_at: u64) {}
+
+    default fn _emit_change_cancelled(&self, _id: u32) {}
+
+    default fn _emit_change_executed(&self, _id: u32) {}
+
+    default fn _schedule_change(&mut self, call: Vec<u8>) -> u32 {
+        let now = ink_env::block_timestamp::<ink_env::DefaultEnvironment>();
+        let data = self.data::<Data>();
+        let id = data.next_change_id;
+        let executable_at = now + data.min_delay;
+
+        data.changes.insert(
+            &id,
+            &ScheduledChange {
+                call,
+                scheduled_by: ink_env::caller::<ink_env::DefaultEnvironment>(),
+                scheduled_at: now,
+                executable_at,
+            },
+        );
+        data.scheduled_ids.push(id);
+        data.next_change_id += 1;
+
+        self._emit_change_scheduled(id, executable_at);
+        id
+    }
+
+    default fn _take_ready_change(&mut self, id: u32) -> Result<Vec<u8>, TimelockError> {
+        let change = self
+            .data::<Data>()
+            .changes
+            .get(&id)
+            .ok_or(TimelockError::UnknownChange)?;
+        if ink_env::block_timestamp::<ink_env::DefaultEnvironment>() < change.executable_at {
+            return Err(TimelockError::ChangeNotReady);
+        }
+        self._remove_change(id)?;
+        Ok(change.call)
+    }
+
+    default fn _remove_change(&mut self, id: u32) -> Result<ScheduledChange, TimelockError> {
+        let data = self.data::<Data>();
+        let change = data.changes.get(&id).ok_or(TimelockError::UnknownChange)?;
+        data.changes.remove(&id);
+        data.scheduled_ids
+            .retain(|scheduled_id| *scheduled_id != id);
+        Ok(change)
+    }
+
+    default fn _set_min_delay(&mut self, min_delay: u64) {
+        self.data::<Data>().min_delay = min_delay;
+    }
+}
@@ -1,3 +1,4 @@
 pub mod factory;
 pub mod oracle_dex;
+pub mod timelock;
 pub mod tr_manager;
@@ -1,118 +1,628 @@
 use ink_prelude::{vec, vec::Vec};
 
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+
 use openbrush::{
-    contracts::traits::{access_control::*, psp22::*},
+    contracts::traits::{access_control::*, pausable::*, psp22::*},
     traits::{AccountId, Balance, String},
 };
 
+use crate::traits::timelock::TimelockError;
+
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+
+/// Scale the oracle stores every price with
+pub const PRICE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Direction amounts are rounded in when converted with a Price
+#[derive(Debug, Clone, Copy, scale::Encode, scale::Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Fixed point price: mantissa / scale quote tokens for one whole base token,
+/// e.g. 6.37 USDT per DOT is Price { mantissa: 6_370_000_000_000_000_000, scale: PRICE_SCALE }.
+/// Conversions take the token decimals so they work on raw token amounts
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct Price {
+    pub mantissa: u128,
+    pub scale: u128,
+}
+
+impl Price {
+    pub fn new(mantissa: u128, scale: u128) -> Self {
+        Price { mantissa, scale }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0 || self.scale == 0
+    }
+
+    /// Same price with another scale, rounded down
+    pub fn rescale(&self, scale: u128) -> Option<Price> {
+        Some(Price {
+            mantissa: mul_div(self.mantissa, scale, self.scale, Rounding::Down)?,
+            scale,
+        })
+    }
+
+    /// Price of the reversed pair, quote per base becoming base per quote, rounded down
+    pub fn inverse(&self) -> Option<Price> {
+        Some(Price {
+            mantissa: mul_div(self.scale, self.scale, self.mantissa, Rounding::Down)?,
+            scale: self.scale,
+        })
+    }
+
+    /// Chains A/B with B/C into A/C at this price's scale, rounded down
+    pub fn chain(&self, next: &Price) -> Option<Price> {
+        Some(Price {
+            mantissa: mul_div(self.mantissa, next.mantissa, next.scale, Rounding::Down)?,
+            scale: self.scale,
+        })
+    }
+
+    /// Raw quote token amount worth base_amount raw base tokens
+    pub fn quote_amount(
+        &self,
+        base_amount: Balance,
+        base_decimals: u8,
+        quote_decimals: u8,
+        rounding: Rounding,
+    ) -> Option<Balance> {
+        let (numerator, denominator) = decimals_factors(base_decimals, quote_decimals)?;
+        mul_div(
+            base_amount,
+            self.mantissa.checked_mul(numerator)?,
+            self.scale.checked_mul(denominator)?,
+            rounding,
+        )
+    }
+
+    /// Raw base token amount worth quote_amount raw quote tokens. None for a zero price
+    pub fn base_amount(
+        &self,
+        quote_amount: Balance,
+        base_decimals: u8,
+        quote_decimals: u8,
+        rounding: Rounding,
+    ) -> Option<Balance> {
+        let (numerator, denominator) = decimals_factors(base_decimals, quote_decimals)?;
+        mul_div(
+            quote_amount,
+            self.scale.checked_mul(denominator)?,
+            self.mantissa.checked_mul(numerator)?,
+            rounding,
+        )
+    }
+}
+
+/// A price with the block it was reported in
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PriceData {
+    pub price: Price,
+    pub timestamp: u64,
+    pub block_number: u32,
+}
+
+/// Basis points of a whole, used for fees and deviations
+pub const BPS: u128 = 10_000;
+
+/// How swaps of a pair are priced. Both fill against the pair's reserves, which its liquidity providers own.
+/// OraclePrice fills at the reported price, ConstantProduct keeps base_reserve * quote_reserve constant
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub enum PricingMode {
+    #[default]
+    OraclePrice,
+    ConstantProduct,
+}
+
+/// Reserves of a ConstantProduct pair
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PairReserves {
+    pub base_reserve: Balance,
+    pub quote_reserve: Balance,
+}
+
+/// Most tokens in a swap path, i.e. at most MAX_PATH_LENGTH - 1 hops
+pub const MAX_PATH_LENGTH: u32 = 4;
+
+/// Most observations kept per pair, bounding the history a twap can span
+pub const MAX_PRICE_OBSERVATIONS: u32 = 64;
+
+/// A published price of a pair. cumulative_price is the sum of mantissa * ms the pair spent at
+/// each previous price up to timestamp, wrapping on overflow. price is the mantissa from timestamp on
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PriceObservation {
+    pub timestamp: u64,
+    pub cumulative_price: u128,
+    pub price: u128,
+}
+
+/// Submissions collected for a pair. Submissions are mantissas at PRICE_SCALE
+#[derive(
+    Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PriceRound {
+    pub id: u32,
+    pub started_at: u64,
+    pub submissions: Vec<(AccountId, u128)>,
+    pub finalized: bool,
+}
+
+/// How a reporter has been doing across rounds
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct ReporterStats {
+    pub submissions: u32,
+    pub missed_rounds: u32,
+    pub outliers: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum OracleDexError {
+    AccessControlError(AccessControlError),
+    /// The pair never got a price or its price is zero
+    NoPrice,
+    /// The last price is older than the max_age asked for
+    StalePrice,
+    /// Pair is not active
+    PairNotActive,
+    /// Price is zero or cannot be expressed at PRICE_SCALE
+    InvalidPrice,
+    /// Reporter already submitted to the current round
+    AlreadySubmitted,
+    /// quorum and round_window must not be zero and reporters must be able to reach the quorum
+    InvalidRoundParams,
+    /// twap window must not be zero
+    InvalidTwapWindow,
+    /// Pair's reserves cannot cover the swap
+    InsufficientLiquidity,
+    /// Swap would return nothing
+    InsufficientOutputAmount,
+    /// Constant product fill strays from the oracle price more than max_swap_deviation_bps
+    PriceDeviation,
+    /// lp_fee_bps must be below BPS
+    InvalidSwapParams,
+    /// Swap would return less than min_amount_out
+    SlippageExceeded,
+    /// Swap arrived after its deadline
+    DeadlineExpired,
+    /// Token has no registered pool
+    PoolNotRegistered,
+    /// Deposit too small to mint a share, or more shares redeemed than held
+    InsufficientShares,
+    /// Shares are outstanding but the pair's reserves are empty. Redeeming them lets the pool start over
+    EmptyPool,
+    /// Caller has not approved the amount to be deposited
+    InsufficientAllowance,
+    /// PSP22 transfer or approval failed or reverted
+    TransferFailed,
+    /// Path is shorter than 2 tokens, longer than MAX_PATH_LENGTH or repeats a token
+    InvalidPath,
+    TimelockError(TimelockError),
+    /// Oracle is paused, or unpause was called while it is not
+    PausableError(PausableError),
+}
+
+impl From<AccessControlError> for OracleDexError {
+    fn from(error: AccessControlError) -> Self {
+        OracleDexError::AccessControlError(error)
+    }
+}
+
+impl From<PausableError> for OracleDexError {
+    fn from(error: PausableError) -> Self {
+        OracleDexError::PausableError(error)
+    }
+}
+
+impl From<TimelockError> for OracleDexError {
+    fn from(error: TimelockError) -> Self {
+        match error {
+            TimelockError::AccessControlError(error) => OracleDexError::AccessControlError(error),
+            error => OracleDexError::TimelockError(error),
+        }
+    }
+}
+
+/// Factors moving a raw amount from base decimals to quote decimals
+fn decimals_factors(base_decimals: u8, quote_decimals: u8) -> Option<(u128, u128)> {
+    if quote_decimals >= base_decimals {
+        Some((
+            10u128.checked_pow((quote_decimals - base_decimals) as u32)?,
+            1,
+        ))
+    } else {
+        Some((
+            1,
+            10u128.checked_pow((base_decimals - quote_decimals) as u32)?,
+        ))
+    }
+}
+
+/// a * b / c rounded as asked. The product is kept in 256 bits so only a result above u128 fails.
+/// None when c is 0 or the result overflows
+pub fn mul_div(a: u128, b: u128, c: u128, rounding: Rounding) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let (quotient, remainder) = match a.checked_mul(b) {
+        Some(product) => (product / c, product % c),
+        None => {
+            let (high, low) = full_mul(a, b);
+            div_256_by_128(high, low, c)?
+        }
+    };
+    match rounding {
+        Rounding::Up if remainder > 0 => quotient.checked_add(1),
+        _ => Some(quotient),
+    }
+}
+
+/// a * b as (high, low) 128 bit halves
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & mask);
+    let (b_high, b_low) = (b >> 64, b & mask);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    let cross = (low_low >> 64) + (high_low & mask) + (low_high & mask);
+    let low = (cross << 64) | (low_low & mask);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (cross >> 64);
+    (high, low)
+}
+
+/// (high, low) / divisor as (quotient, remainder). None if the quotient does not fit in 128 bits
+fn div_256_by_128(high: u128, low: u128, divisor: u128) -> Option<(u128, u128)> {
+    if high >= divisor {
+        return None;
+    }
+    let mut remainder = high;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
 #[openbrush::wrapper]
 pub type OracleDexRef = dyn OracleDex;
 
 #[openbrush::trait_definition]
 pub trait OracleDex {
+    /// Reporter submission to the pair's current round, ORACLE_REPORTER only
     #[ink(message)]
     fn update_price(
         &mut self,
         base_token: AccountId,
         quote_token: AccountId,
-        price: Balance,
-    ) -> Result<(), AccessControlError>;
+        price: Price,
+    ) -> Result<(), OracleDexError>;
+
+    /// Last price of the pair at PRICE_SCALE, zero if there is none
+    #[ink(message)]
+    fn get_pair_price(&self, base_token: AccountId, quote_token: AccountId) -> Price;
+
+    /// Last price of the pair with the timestamp and block number it was reported at
+    #[ink(message)]
+    fn get_pair_price_data(
+        &self,
+        base_token: AccountId,
+        quote_token: AccountId,
+    ) -> Option<PriceData>;
 
+    /// Last price of the pair if it is not zero and was reported at most max_age ms ago
     #[ink(message)]
-    fn get_pair_price(&self, base_token: AccountId, quote_token: AccountId) -> Balance;
+    fn get_pair_price_checked(
+        &self,
+        base_token: AccountId,
+        quote_token: AccountId,
+        max_age: u64,
+    ) -> Result<Price, OracleDexError>;
+
+    /// Average price of the pair if it is not zero and the pair was updated at most max_age ms ago
+    #[ink(message)]
+    fn get_average_price_checked(
+        &self,
+        base_token: AccountId,
+        quote_token: AccountId,
+        max_age: u64,
+    ) -> Result<Price, OracleDexError>;
+
+    /// Twap of the pair over window ms if it is not zero and the pair was updated at most max_age ms ago
+    #[ink(message)]
+    fn get_twap_checked(
+        &self,
+        base_token: AccountId,
+        quote_token: AccountId,
+        window: u64,
+        max_age: u64,
+    ) -> Result<Price, OracleDexError>;
 
     /// returns if pair state is true (accepting prices) or false
     #[ink(message)]
     fn get_pair_state(&self, base_token: AccountId, quote_token: AccountId) -> bool;
 
-    /// returns liquidity per token
+    /// returns liquidity per token, held across the reserves of every pair
     #[ink(message)]
     fn get_pool_liquidity(&self, token_address: AccountId) -> Balance;
 
     #[ink(message)]
     fn get_pool_state(&self, token_address: AccountId) -> bool;
 
-    /// Call to change pair state to true from false to ro register it for 1st time so we can interact with it
+    /// Schedule changing pair state to true from false to register it for 1st time so we can interact with it.
+    /// The contract's execute_change applies it once the timelock delay has passed
     #[ink(message)]
     fn activate_pair(
         &mut self,
         base_token: AccountId,
         quote_token: AccountId,
-    ) -> Result<(), AccessControlError>;
+    ) -> Result<(), OracleDexError>;
 
-    /// Pause pair so we cannot interact
+    /// Pause pair so we cannot interact. Takes effect immediately
     #[ink(message)]
     fn pause_pair(
         &mut self,
         base_token: AccountId,
         quote_token: AccountId,
-    ) -> Result<(), AccessControlError>;
+    ) -> Result<(), OracleDexError>;
 
-    /// rgister pool for new token
+    /// rgister pool for new token. Pairs are activated and take liquidity only between registered pools
     #[ink(message)]
-    fn register_pool(&mut self, token: AccountId) -> Result<(), AccessControlError>;
+    fn register_pool(&mut self, token: AccountId) -> Result<(), OracleDexError>;
 
-    /// Add liquidity for token. Pool must be already registered
+    /// Add liquidity to the pair's reserves from the caller, who must have approved both amounts. Both tokens
+    /// must have registered pools and the pair must be active as (base_token, quote_token). The first deposit sets the reserves' ratio, later ones
+    /// take at most base_amount and quote_amount in the current ratio. Returns the pool shares credited to the caller
     #[ink(message)]
     fn add_pool_liquidity(
         &mut self,
-        token: AccountId,
-        amount: Balance,
-    ) -> Result<(), AccessControlError>;
+        base_token: AccountId,
+        quote_token: AccountId,
+        base_amount: Balance,
+        quote_amount: Balance,
+    ) -> Result<Balance, OracleDexError>;
 
-    /// Withdraw liquidity from token pool
+    /// Redeem the caller's shares for their part of both reserves, swap fees included.
+    /// Returns the (base, quote) amounts paid out
     #[ink(message)]
     fn withdraw_pool_liquidity(
         &mut self,
-        token: AccountId,
-        amount: Balance,
-    ) -> Result<(), AccessControlError>;
+        base_token: AccountId,
+        quote_token: AccountId,
+        shares: Balance,
+    ) -> Result<(Balance, Balance), OracleDexError>;
+
+    /// Shares provider holds in the pair's pool
+    #[ink(message)]
+    fn get_pool_shares(
+        &self,
+        base_token: AccountId,
+        quote_token: AccountId,
+        provider: AccountId,
+    ) -> Balance;
+
+    #[ink(message)]
+    fn get_pool_total_shares(&self, base_token: AccountId, quote_token: AccountId) -> Balance;
 
-    ///Swap one token for another
+    ///Swap one token for another, priced by the pair's PricingMode. Fails if it would return less than
+    ///min_amount_out or the block timestamp is past deadline (ms)
     #[ink(message)]
     fn swap(
         &mut self,
         deposited_token: AccountId,
         withdrawn_token: AccountId,
         amount: Balance,
-        swap_caller: AccountId,
+        min_amount_out: Balance,
+        deadline: u64,
         use_average_price: bool,
-    ) -> Result<Balance, AccessControlError>;
+    ) -> Result<Balance, OracleDexError>;
+
+    ///Swap amount of path[0] through every pair along path for the last token of path. Fails if it would
+    ///return less than min_amount_out or the block timestamp is past deadline (ms)
+    #[ink(message)]
+    fn swap_exact_in(
+        &mut self,
+        path: Vec<AccountId>,
+        amount: Balance,
+        min_amount_out: Balance,
+        deadline: u64,
+    ) -> Result<Balance, OracleDexError>;
 
-    ///Get length of desired moving average
+    ///What swap_exact_in along path returns for amount_in at the pairs' current pricing
     #[ink(message)]
-    fn get_average_length(&self) -> u8;
+    fn get_amount_out_path(
+        &self,
+        path: Vec<AccountId>,
+        amount_in: Balance,
+    ) -> Result<Balance, OracleDexError>;
+
+    ///Path through active pairs, at most MAX_PATH_LENGTH tokens, returning the most token_out for amount_in
+    ///together with that amount. None if no path can fill amount_in
+    #[ink(message)]
+    fn get_best_route(
+        &self,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount_in: Balance,
+    ) -> Option<(Vec<AccountId>, Balance)>;
 
-    /// show where next pointer is in stored values to calcualate average
-    // #[ink(message)]
-    fn get_average_prices_pointer(&self, base_token: AccountId, quote_token: AccountId) -> u8;
+    ///Last or average price of path[0] in the last token of path, chaining the price of every pair along
+    ///path whichever way round it is registered. Every price must be at most max_age ms old
+    #[ink(message)]
+    fn get_route_price_checked(
+        &self,
+        path: Vec<AccountId>,
+        use_average_price: bool,
+        max_age: u64,
+    ) -> Result<Price, OracleDexError>;
 
-    // #[ink(message)]
-    fn set_average_prices_pointer(
+    ///ADMIN only. The pair must have been activated as (base_token, quote_token).
+    ///Schedules the change, the contract's execute_change applies it once the timelock delay has passed
+    #[ink(message)]
+    fn set_pricing_mode(
         &mut self,
         base_token: AccountId,
         quote_token: AccountId,
-        new_pointer: u8,
-    );
+        mode: PricingMode,
+    ) -> Result<(), OracleDexError>;
 
-    ///Call this to update averag price, it will also update the last price accessible via get_pair_price
     #[ink(message)]
-    fn update_average_price(
+    fn get_pricing_mode(&self, base_token: AccountId, quote_token: AccountId) -> PricingMode;
+
+    #[ink(message)]
+    fn get_pair_reserves(&self, base_token: AccountId, quote_token: AccountId) -> PairReserves;
+
+    ///ADMIN only. lp_fee_bps of every swap's amount in is kept by the pair's reserves and
+    ///max_swap_deviation_bps bounds how far constant product fills may be from the last oracle price.
+    ///Scheduled like set_pricing_mode
+    #[ink(message)]
+    fn set_swap_params(
         &mut self,
-        base_token: AccountId,
-        quote_token: AccountId,
-        price: Balance,
-    ) -> Result<(), AccessControlError>;
+        lp_fee_bps: u16,
+        max_swap_deviation_bps: u16,
+    ) -> Result<(), OracleDexError>;
+
+    ///Returns (lp_fee_bps, max_swap_deviation_bps)
+    #[ink(message)]
+    fn get_swap_params(&self) -> (u16, u16);
 
-    ///Returns average price for a pair
+    ///withdrawn_token a swap of amount_in deposited_token returns at the pair's current pricing
     #[ink(message)]
-    fn get_average_price(&self, base_token: AccountId, quote_token: AccountId) -> Balance;
+    fn get_amount_out(
+        &self,
+        deposited_token: AccountId,
+        withdrawn_token: AccountId,
+        amount_in: Balance,
+    ) -> Result<Balance, OracleDexError>;
 
-    ///Returns the constiutent prices stored for calculating moving average for the average price of the pair
+    ///deposited_token a swap needs to return amount_out withdrawn_token at the pair's current pricing
     #[ink(message)]
-    fn get_average_prices_constituents(
+    fn get_amount_in(
+        &self,
+        deposited_token: AccountId,
+        withdrawn_token: AccountId,
+        amount_out: Balance,
+    ) -> Result<Balance, OracleDexError>;
+
+    ///Window in ms the average price is taken over
+    #[ink(message)]
+    fn get_twap_window(&self) -> u64;
+
+    ///ADMIN only. Scheduled like set_pricing_mode
+    #[ink(message)]
+    fn set_twap_window(&mut self, window: u64) -> Result<(), OracleDexError>;
+
+    ///Time weighted average price of the pair over the last window ms at PRICE_SCALE.
+    ///A window older than the observations kept is shortened to them
+    #[ink(message)]
+    fn get_twap(&self, base_token: AccountId, quote_token: AccountId, window: u64) -> Price;
+
+    ///Observations the twap is calculated from, oldest first
+    #[ink(message)]
+    fn get_price_observations(
         &self,
         base_token: AccountId,
         quote_token: AccountId,
-    ) -> Vec<Balance>;
+    ) -> Vec<PriceObservation>;
+
+    ///Same submission as update_price. A published median updates both the last price and the twap
+    #[ink(message)]
+    fn update_average_price(
+        &mut self,
+        base_token: AccountId,
+        quote_token: AccountId,
+        price: Price,
+    ) -> Result<(), OracleDexError>;
+
+    ///Returns the twap of the pair over the twap window at PRICE_SCALE
+    #[ink(message)]
+    fn get_average_price(&self, base_token: AccountId, quote_token: AccountId) -> Price;
 }
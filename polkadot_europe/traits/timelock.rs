@@ -0,0 +1,63 @@
+use ink_prelude::vec::Vec;
+
+use ink_storage::traits::{PackedLayout, SpreadLayout};
+
+use openbrush::{contracts::traits::access_control::*, traits::AccountId};
+
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+
+/// ms a privileged change waits before it can be executed, unless the contract sets another delay
+pub const DEFAULT_TIMELOCK_DELAY: u64 = 172_800_000;
+
+/// Privileged change waiting for its delay. call is the scale encoded change of the contract that scheduled it
+#[derive(
+    Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct ScheduledChange {
+    pub call: Vec<u8>,
+    pub scheduled_by: AccountId,
+    pub scheduled_at: u64,
+    pub executable_at: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum TimelockError {
+    AccessControlError(AccessControlError),
+    /// No change is scheduled with this id
+    UnknownChange,
+    /// The change's delay has not passed yet
+    ChangeNotReady,
+    /// The scheduled call cannot be decoded or applied by the contract
+    InvalidChange,
+}
+
+impl From<AccessControlError> for TimelockError {
+    fn from(error: AccessControlError) -> Self {
+        TimelockError::AccessControlError(error)
+    }
+}
+
+#[openbrush::wrapper]
+pub type TimelockRef = dyn Timelock;
+
+#[openbrush::trait_definition]
+pub trait Timelock {
+    /// ms every change waits between being scheduled and becoming executable. Changing it is itself a
+    /// scheduled change, and changes already scheduled keep their executable_at
+    #[ink(message)]
+    fn get_min_delay(&self) -> u64;
+
+    /// Ids of the changes neither executed nor cancelled yet, oldest first
+    #[ink(message)]
+    fn get_scheduled_change_ids(&self) -> Vec<u32>;
+
+    #[ink(message)]
+    fn get_scheduled_change(&self, id: u32) -> Option<ScheduledChange>;
+
+    /// GUARDIAN only. Drops a scheduled change before it is executed
+    #[ink(message)]
+    fn cancel_change(&mut self, id: u32) -> Result<(), TimelockError>;
+}
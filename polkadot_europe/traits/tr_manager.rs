@@ -2,10 +2,12 @@ use ink_prelude::{vec, vec::Vec};
 use ink_storage::traits::{PackedLayout, SpreadLayout};
 
 use openbrush::{
-    contracts::traits::{access_control::*, psp22::*},
+    contracts::traits::{access_control::*, pausable::*, psp22::*},
     traits::{AccountId, Balance, String},
 };
 
+use crate::traits::{oracle_dex::OracleDexError, timelock::TimelockError};
+
 #[cfg(feature = "std")]
 use ink_storage::traits::StorageLayout;
 
@@ -17,6 +19,381 @@ pub enum PaymentType {
     #[default]
     OneOffFutureTime,
     Instalments,
+    /// requested_value accrues linearly between the start and end timestamps (ms) and is claimed by the payees
+    Streaming {
+        start: u64,
+        end: u64,
+    },
+}
+
+#[derive(
+    Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PaymentPlan {
+    /// Amount paid at each payment_schedule timestamp. Leave empty to divide requested_value evenly,
+    /// the rounding remainder going to the last instalment
+    pub instalment_amounts: Vec<Balance>,
+    /// Nothing is paid before this timestamp. Instalments that fall before it are paid together once it passes
+    pub cliff: Option<u64>,
+}
+
+/// Where a job is in its life. Open jobs wait for their first payment to become due,
+/// Pending and PartiallyPaid jobs accrue and are paid, Failed jobs are parked after too many failed payments
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub enum JobStatus {
+    #[default]
+    Open,
+    Pending,
+    PartiallyPaid,
+    Completed,
+    Cancelled,
+    Failed,
+    Paused,
+}
+
+impl JobStatus {
+    /// Legal edges of the job state machine. Completed and Cancelled are final
+    pub fn can_transition_to(&self, next: &JobStatus) -> bool {
+        use JobStatus::*;
+        matches!(
+            (self, next),
+            (Open, Pending)
+                | (Open, Cancelled)
+                | (Open, Paused)
+                | (Pending, PartiallyPaid)
+                | (Pending, Completed)
+                | (Pending, Failed)
+                | (Pending, Open)
+                | (Pending, Cancelled)
+                | (Pending, Paused)
+                | (PartiallyPaid, Completed)
+                | (PartiallyPaid, Failed)
+                | (PartiallyPaid, Open)
+                | (PartiallyPaid, Cancelled)
+                | (PartiallyPaid, Paused)
+                | (Failed, Pending)
+                | (Failed, PartiallyPaid)
+                | (Failed, Cancelled)
+                | (Paused, Open)
+                | (Paused, Cancelled)
+        )
+    }
+}
+
+/// Criteria for TreasuryManager::get_jobs. None matches every job.
+/// payment_type matches on the variant only, the Streaming window is ignored.
+/// A job is in the due range if any of its payment_schedule timestamps, or its stream window, falls in [due_from, due_to]
+#[derive(Default, Debug, Clone, scale::Encode, scale::Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct JobFilter {
+    pub status: Option<JobStatus>,
+    pub applicant: Option<AccountId>,
+    pub payee: Option<AccountId>,
+    pub requested_token: Option<AccountId>,
+    pub payment_type: Option<PaymentType>,
+    pub due_from: Option<u64>,
+    pub due_to: Option<u64>,
+}
+
+/// Window TreasuryManager::calculate_liabilities sums payments over, with its own thresholds. Dues already
+/// accrued and not paid yet count in every window. warning and critical are percentages of the treasury
+/// balance the liability must exceed to breach them, critical being the higher one.
+/// top_up_target is the percentage of the liability the balance should be topped up to once critical
+#[derive(
+    Default, Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct LiabilityHorizon {
+    pub name: String,
+    /// ms ahead of now. u64::MAX covers every payment still to be made
+    pub duration: u64,
+    pub warning: u8,
+    pub critical: u8,
+    pub top_up_target: u16,
+}
+
+/// Outcome of one batch of a sweep. processed counts the jobs looked at, completed the ones that changed
+/// status, accrued or were paid. remaining is 0 once the sweep has reached the end and will start over
+#[derive(Default, Debug, Clone, Copy, scale::Encode, scale::Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct BatchProgress {
+    pub processed: u32,
+    pub completed: u32,
+    pub remaining: u32,
+}
+
+/// Role handed over to account once it accepts, at the latest at deadline (ms)
+#[derive(
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    scale::Encode,
+    scale::Decode,
+    SpreadLayout,
+    PackedLayout,
+    PartialEq,
+    Eq,
+)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct PendingHandover {
+    pub account: AccountId,
+    pub deadline: u64,
+}
+
+impl PendingHandover {
+    /// Checks that caller is the proposed account and the deadline has not passed at now
+    pub fn check_acceptance(
+        &self,
+        caller: AccountId,
+        now: u64,
+    ) -> Result<(), TreasuryManagerError> {
+        if self.account != caller {
+            return Err(TreasuryManagerError::NotPendingAccount);
+        }
+        if now > self.deadline {
+            return Err(TreasuryManagerError::HandoverExpired);
+        }
+        Ok(())
+    }
+}
+
+/// ms a proposed ADMIN or MANAGER has to accept the role
+pub const HANDOVER_WINDOW: u64 = 604_800_000;
+
+/// Sensitive TreasuryManager calls. They run once approval_threshold approvers have approved them
+#[derive(Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub enum Operation {
+    /// Transfer amount treasury tokens to to
+    AdminWithdrawal {
+        amount: Balance,
+        to: AccountId,
+    },
+    /// Terminate the contract, sending its native balance to the ADMIN
+    TerminateMe,
+    ProposeManager {
+        account: AccountId,
+    },
+    ProposeAdmin {
+        account: AccountId,
+    },
+    /// Scheduled on the timelock, applied through execute_change once its delay has passed
+    SetOracleDexAddress {
+        oracle_dex_address: AccountId,
+    },
+    RegisterForeignAsset {
+        token_symbol: String,
+        token_address: AccountId,
+    },
+    /// threshold of approvers must approve every later operation. approvers must include the current ADMIN
+    SetApprovers {
+        approvers: Vec<AccountId>,
+        threshold: u8,
+    },
+    /// Grant GUARDIAN, which can cancel scheduled changes
+    AddGuardian {
+        account: AccountId,
+    },
+    RemoveGuardian {
+        account: AccountId,
+    },
+    /// Scheduled like SetOracleDexAddress, so the new timelock delay waits out the current one
+    SetMinDelay {
+        min_delay: u64,
+    },
+}
+
+/// Hash of the scale encoded Operation identifying its proposal
+pub type OperationHash = [u8; 32];
+
+/// Operation waiting for approvals until expires_at (ms)
+#[derive(Debug, Clone, scale::Encode, scale::Decode, SpreadLayout, PackedLayout, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(StorageLayout, scale_info::TypeInfo))]
+pub struct Proposal {
+    pub operation: Operation,
+    pub proposer: AccountId,
+    pub approvals: Vec<AccountId>,
+    pub expires_at: u64,
+}
+
+/// ms a proposal can collect approvals for
+pub const PROPOSAL_LIFETIME: u64 = 604_800_000;
+
+/// payee_shares are basis points of every payment and must add up to this value
+pub const PAYEE_SHARES_TOTAL: u16 = 10_000;
+
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum TreasuryManagerError {
+    AccessControlError(AccessControlError),
+    PSP22(PSP22Error),
+    /// No job is stored under the given id
+    UnknownJob,
+    /// requested_token is neither the treasury token nor a registered foreign asset
+    UnregisteredToken,
+    /// Job has no payee_accounts or is OneOffFutureTime with more than one timestamp
+    InvalidJob,
+    /// payment_schedule is empty
+    EmptySchedule,
+    /// payment_schedule timestamps are not strictly increasing
+    ScheduleNotIncreasing,
+    /// instalment_amounts do not match payment_schedule or do not add up to requested_value
+    InstalmentAmountsMismatch,
+    /// cliff is later than the last payment_schedule timestamp
+    InvalidCliff,
+    /// Streaming end is not after start
+    InvalidStreamWindow,
+    /// Job is not of PaymentType::Streaming
+    NotStreamingJob,
+    /// Caller is not one of the job's payee_accounts
+    NotPayee,
+    /// Nothing has accrued since the last claim
+    NothingToClaim,
+    /// Job is completed, cancelled or otherwise not in a state that allows the call
+    InvalidJobState,
+    /// Amount could not be converted with the oracle price without overflowing
+    PriceConversionFailed,
+    /// Oracle price is older than the treasury's max_price_age
+    StalePrice,
+    /// Oracle returned no usable price for the pair
+    OracleUnavailable,
+    SwapFailed,
+    InsufficientTreasuryBalance,
+    /// No liability horizons, too many, or their warning, critical and top up percentages are out of order
+    InvalidThresholds,
+    /// Cross contract call reverted or could not be decoded
+    TransferFailed,
+    /// Job is not parked in the failed jobs list, nor Cancelled with dues left
+    JobNotFailed,
+    /// payee_shares do not match payee_accounts or do not add up to PAYEE_SHARES_TOTAL
+    InvalidPayeeShares,
+    /// Swap delivered less than its min_amount_out
+    SlippageExceeded,
+    /// Swap was executed after its deadline
+    SwapDeadlineExpired,
+    /// swap_tolerance_bps must be below BPS
+    InvalidSwapTolerance,
+    /// Payment route does not lead from the treasury token to the token or is longer than MAX_PATH_LENGTH
+    InvalidPaymentRoute,
+    /// No ADMIN or MANAGER handover is waiting to be accepted
+    NoPendingHandover,
+    /// Caller is not the account the role was proposed to
+    NotPendingAccount,
+    /// Handover was not accepted before its deadline
+    HandoverExpired,
+    /// Caller is not one of the approvers
+    NotApprover,
+    /// Same operation is already waiting for approvals
+    ProposalExists,
+    /// No proposal with this hash
+    UnknownProposal,
+    /// Caller already approved the proposal
+    AlreadyApproved,
+    /// Proposal expired before reaching the approval threshold
+    ProposalExpired,
+    /// Approvers are empty, repeated or leave out the ADMIN, or the threshold is 0 or above their number
+    InvalidApprovers,
+    Timelock(TimelockError),
+    /// Contract is paused, or unpause was called while it is not
+    PausableError(PausableError),
+}
+
+impl TreasuryManagerError {
+    /// Compact code kept on a job as its last payment error. 0 is reserved for "no error"
+    pub fn code(&self) -> u8 {
+        match self {
+            TreasuryManagerError::AccessControlError(_) => 1,
+            TreasuryManagerError::PSP22(_) => 2,
+            TreasuryManagerError::UnknownJob => 3,
+            TreasuryManagerError::UnregisteredToken => 4,
+            TreasuryManagerError::InvalidJob => 5,
+            TreasuryManagerError::OracleUnavailable => 6,
+            TreasuryManagerError::SwapFailed => 7,
+            TreasuryManagerError::InsufficientTreasuryBalance => 8,
+            TreasuryManagerError::InvalidThresholds => 9,
+            TreasuryManagerError::TransferFailed => 10,
+            TreasuryManagerError::JobNotFailed => 11,
+            TreasuryManagerError::InvalidPayeeShares => 12,
+            TreasuryManagerError::EmptySchedule => 13,
+            TreasuryManagerError::ScheduleNotIncreasing => 14,
+            TreasuryManagerError::InstalmentAmountsMismatch => 15,
+            TreasuryManagerError::InvalidCliff => 16,
+            TreasuryManagerError::InvalidStreamWindow => 17,
+            TreasuryManagerError::NotStreamingJob => 18,
+            TreasuryManagerError::NotPayee => 19,
+            TreasuryManagerError::NothingToClaim => 20,
+            TreasuryManagerError::InvalidJobState => 21,
+            TreasuryManagerError::PriceConversionFailed => 22,
+            TreasuryManagerError::StalePrice => 23,
+            TreasuryManagerError::SlippageExceeded => 24,
+            TreasuryManagerError::SwapDeadlineExpired => 25,
+            TreasuryManagerError::InvalidSwapTolerance => 26,
+            TreasuryManagerError::InvalidPaymentRoute => 27,
+            TreasuryManagerError::NoPendingHandover => 28,
+            TreasuryManagerError::NotPendingAccount => 29,
+            TreasuryManagerError::HandoverExpired => 30,
+            TreasuryManagerError::NotApprover => 31,
+            TreasuryManagerError::ProposalExists => 32,
+            TreasuryManagerError::UnknownProposal => 33,
+            TreasuryManagerError::AlreadyApproved => 34,
+            TreasuryManagerError::ProposalExpired => 35,
+            TreasuryManagerError::InvalidApprovers => 36,
+            TreasuryManagerError::Timelock(_) => 37,
+            TreasuryManagerError::PausableError(_) => 38,
+        }
+    }
+}
+
+impl From<AccessControlError> for TreasuryManagerError {
+    fn from(error: AccessControlError) -> Self {
+        TreasuryManagerError::AccessControlError(error)
+    }
+}
+
+impl From<PausableError> for TreasuryManagerError {
+    fn from(error: PausableError) -> Self {
+        TreasuryManagerError::PausableError(error)
+    }
+}
+
+impl From<TimelockError> for TreasuryManagerError {
+    fn from(error: TimelockError) -> Self {
+        match error {
+            TimelockError::AccessControlError(error) => {
+                TreasuryManagerError::AccessControlError(error)
+            }
+            error => TreasuryManagerError::Timelock(error),
+        }
+    }
+}
+
+impl From<OracleDexError> for TreasuryManagerError {
+    fn from(error: OracleDexError) -> Self {
+        match error {
+            OracleDexError::StalePrice => TreasuryManagerError::StalePrice,
+            _ => TreasuryManagerError::OracleUnavailable,
+        }
+    }
+}
+
+impl From<PSP22Error> for TreasuryManagerError {
+    fn from(error: PSP22Error) -> Self {
+        TreasuryManagerError::PSP22(error)
+    }
 }
 
 #[openbrush::wrapper]
@@ -24,9 +401,52 @@ pub type TreasureManagerRef = dyn TreasureManager;
 
 #[openbrush::trait_definition]
 pub trait TreasureManager {
-    //
+    /// Approvers only. Records operation as a proposal approved by the caller and runs it right away
+    /// if that reaches the threshold. Returns the hash approvals refer to.
+    /// The ADMIN always holds an approver seat: the contract_administrator the contract was created with,
+    /// which is the pallet when launched through it, starts as the only approver, SetApprovers cannot
+    /// leave the ADMIN out and accept_admin hands the seat to the new ADMIN. Other approvers need no role
+    #[ink(message)]
+    fn propose_operation(
+        &mut self,
+        operation: Operation,
+    ) -> Result<OperationHash, TreasuryManagerError>;
+
+    /// Approvers only. Runs the operation once approvals reach the threshold before the proposal expires.
+    /// Only approvals of accounts that are still approvers count
+    #[ink(message)]
+    fn approve_operation(&mut self, hash: OperationHash) -> Result<(), TreasuryManagerError>;
+
+    #[ink(message)]
+    fn get_proposal(&self, hash: OperationHash) -> Option<Proposal>;
+
+    /// Returns approvers and approval threshold
+    #[ink(message)]
+    fn get_approvers(&self) -> (Vec<AccountId>, u8);
+
+    /// Called by the account proposed through Operation::ProposeManager within HANDOVER_WINDOW.
+    /// The previous MANAGER loses the role
+    #[ink(message)]
+    fn accept_manager(&mut self) -> Result<(), TreasuryManagerError>;
+
+    /// ADMIN only
+    #[ink(message)]
+    fn cancel_manager_handover(&mut self) -> Result<(), TreasuryManagerError>;
+
+    /// Called by the account proposed through Operation::ProposeAdmin within HANDOVER_WINDOW.
+    /// The previous ADMIN loses the role, its approver seat and GUARDIAN, which all pass to the caller
     #[ink(message)]
-    fn set_manager(&mut self, account: AccountId) -> Result<(), AccessControlError>;
+    fn accept_admin(&mut self) -> Result<(), TreasuryManagerError>;
+
+    /// ADMIN only
+    #[ink(message)]
+    fn cancel_admin_handover(&mut self) -> Result<(), TreasuryManagerError>;
+
+    #[ink(message)]
+    fn get_pending_manager(&self) -> Option<PendingHandover>;
+
+    #[ink(message)]
+    fn get_pending_admin(&self) -> Option<PendingHandover>;
 
     #[ink(message)]
     fn add_job(
@@ -39,18 +459,150 @@ pub trait TreasureManager {
         requested_value: Balance,
         payment_type: PaymentType,
         payment_schedule: Vec<u64>,
+        payment_plan: PaymentPlan,
+        payee_accounts: Vec<AccountId>,
+        payee_shares: Vec<u16>,
+    ) -> Result<(), TreasuryManagerError>;
+
+    /// Revoke a job that is not completed. Whatever has not accrued yet stays in the treasury,
+    /// dues already accrued, including what a stream released and was not claimed, are still paid to the payees
+    #[ink(message)]
+    fn cancel_job(&mut self, id: u32, reason: String) -> Result<(), TreasuryManagerError>;
+
+    /// Replace what is left to pay on a job. requested_value, payment_schedule and payment_plan
+    /// describe the remaining payments only, amounts already due are kept.
+    /// A stream's unclaimed accrual becomes due and the amended stream runs from now to its end.
+    /// A stream cannot be amended once its end has passed
+    #[ink(message)]
+    fn amend_job(
+        &mut self,
+        id: u32,
+        requested_value: Balance,
+        payment_schedule: Vec<u64>,
+        payment_plan: PaymentPlan,
         payee_accounts: Vec<AccountId>,
-    ) -> Result<(), AccessControlError>;
+        payee_shares: Vec<u16>,
+    ) -> Result<(), TreasuryManagerError>;
 
     #[ink(message)]
-    fn remove_job_info(&mut self, id: u32) -> Result<(), AccessControlError>;
+    fn pause_job(&mut self, id: u32) -> Result<(), TreasuryManagerError>;
 
     #[ink(message)]
-    fn admin_withdrawal(&mut self, amount: Balance) -> Result<(), AccessControlError>;
+    fn resume_job(&mut self, id: u32) -> Result<(), TreasuryManagerError>;
 
     #[ink(message)]
-    fn make_deposit(&mut self, amount: Balance) -> Result<(), AccessControlError>;
+    fn remove_job_info(&mut self, id: u32) -> Result<(), TreasuryManagerError>;
 
     #[ink(message)]
-    fn terminate_me(&mut self) -> Result<(), AccessControlError>;
+    fn make_deposit(&mut self, amount: Balance) -> Result<(), TreasuryManagerError>;
+
+    /// GUARDIAN only. Halts payments, swaps and job transitions until the ADMIN unpauses. reason is logged
+    #[ink(message)]
+    fn pause(&mut self, reason: String) -> Result<(), TreasuryManagerError>;
+
+    /// ADMIN only
+    #[ink(message)]
+    fn unpause(&mut self, reason: String) -> Result<(), TreasuryManagerError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One value of every variant. The match fails to compile when a variant is added without being listed here
+    fn every_error() -> Vec<TreasuryManagerError> {
+        let errors = vec![
+            TreasuryManagerError::AccessControlError(AccessControlError::MissingRole),
+            TreasuryManagerError::PSP22(PSP22Error::InsufficientBalance),
+            TreasuryManagerError::UnknownJob,
+            TreasuryManagerError::UnregisteredToken,
+            TreasuryManagerError::InvalidJob,
+            TreasuryManagerError::OracleUnavailable,
+            TreasuryManagerError::SwapFailed,
+            TreasuryManagerError::InsufficientTreasuryBalance,
+            TreasuryManagerError::InvalidThresholds,
+            TreasuryManagerError::TransferFailed,
+            TreasuryManagerError::JobNotFailed,
+            TreasuryManagerError::InvalidPayeeShares,
+            TreasuryManagerError::EmptySchedule,
+            TreasuryManagerError::ScheduleNotIncreasing,
+            TreasuryManagerError::InstalmentAmountsMismatch,
+            TreasuryManagerError::InvalidCliff,
+            TreasuryManagerError::InvalidStreamWindow,
+            TreasuryManagerError::NotStreamingJob,
+            TreasuryManagerError::NotPayee,
+            TreasuryManagerError::NothingToClaim,
+            TreasuryManagerError::InvalidJobState,
+            TreasuryManagerError::PriceConversionFailed,
+            TreasuryManagerError::StalePrice,
+            TreasuryManagerError::SlippageExceeded,
+            TreasuryManagerError::SwapDeadlineExpired,
+            TreasuryManagerError::InvalidSwapTolerance,
+            TreasuryManagerError::InvalidPaymentRoute,
+            TreasuryManagerError::NoPendingHandover,
+            TreasuryManagerError::NotPendingAccount,
+            TreasuryManagerError::HandoverExpired,
+            TreasuryManagerError::NotApprover,
+            TreasuryManagerError::ProposalExists,
+            TreasuryManagerError::UnknownProposal,
+            TreasuryManagerError::AlreadyApproved,
+            TreasuryManagerError::ProposalExpired,
+            TreasuryManagerError::InvalidApprovers,
+            TreasuryManagerError::Timelock(TimelockError::UnknownChange),
+            TreasuryManagerError::PausableError(PausableError::Paused),
+        ];
+        for error in errors.iter() {
+            match error {
+                TreasuryManagerError::AccessControlError(_)
+                | TreasuryManagerError::PSP22(_)
+                | TreasuryManagerError::UnknownJob
+                | TreasuryManagerError::UnregisteredToken
+                | TreasuryManagerError::InvalidJob
+                | TreasuryManagerError::OracleUnavailable
+                | TreasuryManagerError::SwapFailed
+                | TreasuryManagerError::InsufficientTreasuryBalance
+                | TreasuryManagerError::InvalidThresholds
+                | TreasuryManagerError::TransferFailed
+                | TreasuryManagerError::JobNotFailed
+                | TreasuryManagerError::InvalidPayeeShares
+                | TreasuryManagerError::EmptySchedule
+                | TreasuryManagerError::ScheduleNotIncreasing
+                | TreasuryManagerError::InstalmentAmountsMismatch
+                | TreasuryManagerError::InvalidCliff
+                | TreasuryManagerError::InvalidStreamWindow
+                | TreasuryManagerError::NotStreamingJob
+                | TreasuryManagerError::NotPayee
+                | TreasuryManagerError::NothingToClaim
+                | TreasuryManagerError::InvalidJobState
+                | TreasuryManagerError::PriceConversionFailed
+                | TreasuryManagerError::StalePrice
+                | TreasuryManagerError::SlippageExceeded
+                | TreasuryManagerError::SwapDeadlineExpired
+                | TreasuryManagerError::InvalidSwapTolerance
+                | TreasuryManagerError::InvalidPaymentRoute
+                | TreasuryManagerError::NoPendingHandover
+                | TreasuryManagerError::NotPendingAccount
+                | TreasuryManagerError::HandoverExpired
+                | TreasuryManagerError::NotApprover
+                | TreasuryManagerError::ProposalExists
+                | TreasuryManagerError::UnknownProposal
+                | TreasuryManagerError::AlreadyApproved
+                | TreasuryManagerError::ProposalExpired
+                | TreasuryManagerError::InvalidApprovers
+                | TreasuryManagerError::Timelock(_)
+                | TreasuryManagerError::PausableError(_) => (),
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn error_codes_are_unique_and_never_zero() {
+        let errors = every_error();
+        let mut codes: Vec<u8> = errors.iter().map(|error| error.code()).collect();
+        assert!(codes.iter().all(|code| *code > 0));
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+    }
 }